@@ -1,23 +1,115 @@
 pub mod cli_args {
-    use clap::{Args, Parser, Subcommand};
+    use crate::http::http_client::DownloadConfig;
+    use crate::reader::mrt_reader::STDIN;
+    use crate::ribs::rib_getter::RibFilter;
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use clap::error::ErrorKind;
+    use clap::{Args, CommandFactory, Parser, Subcommand};
 
-    /// Download RIB files by specifying an output folder and a date.
+    /// Parse yyyy-mm-dd or yyyy-mm-ddThh:mm, a date alone is the start of that day
+    fn parse_start_time(value: &str) -> Result<NaiveDateTime, String> {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+            return Ok(datetime);
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| date.and_time(NaiveTime::MIN))
+            .map_err(|e| format!("Expected yyyy-mm-dd or yyyy-mm-ddThh:mm: {}", e))
+    }
+
+    /// Parse yyyy-mm-dd or yyyy-mm-ddThh:mm, a date alone is the end of that day
+    fn parse_end_time(value: &str) -> Result<NaiveDateTime, String> {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+            return Ok(datetime);
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| date.and_hms_opt(23, 59, 59).unwrap())
+            .map_err(|e| format!("Expected yyyy-mm-dd or yyyy-mm-ddThh:mm: {}", e))
+    }
+
+    /// Select RIBs by date or time range, snapshot hour, collector and project
+    #[derive(Debug, Args)]
+    pub struct RibSelectionArgs {
+        /// Get RIBs for yyyy-mm-dd
+        #[arg(short = 'y', long, default_value_t = String::from("2025-09-22"), conflicts_with = "from")]
+        pub ribs_ymd: String,
+
+        /// Get RIBs from yyyy-mm-dd[Thh:mm] onwards (instead of a single day)
+        #[arg(long, value_parser = parse_start_time)]
+        pub from: Option<NaiveDateTime>,
+
+        /// Get RIBs up to yyyy-mm-dd[Thh:mm], defaults to the end of the --from day
+        #[arg(long, value_parser = parse_end_time, requires = "from")]
+        pub to: Option<NaiveDateTime>,
+
+        /// Space seperated list of snapshot hours (UTC) to get, e.g. "0 8 16"
+        #[arg(
+            long,
+            value_delimiter = ' ',
+            num_args = 1..,
+            default_values_t = [0],
+            value_parser = clap::value_parser!(u32).range(0..24)
+        )]
+        pub hours: Vec<u32>,
+
+        /// Space seperated list of collector IDs or globs to get, e.g. "rrc00 route-views*"
+        #[arg(short = 'i', long, value_delimiter = ' ', num_args = 1..)]
+        pub include: Vec<String>,
+
+        /// Space seperated list of collector IDs or globs to skip
+        #[arg(short = 'e', long, value_delimiter = ' ', num_args = 1..)]
+        pub exclude: Vec<String>,
+
+        /// Only get RIBs from this project
+        #[arg(long, value_parser = ["ris", "route-views"])]
+        pub project: Option<String>,
+
+        /// Print the matching RIB URLs, sizes and filenames, without downloading or parsing
+        #[arg(short = 'l', long)]
+        pub list: bool,
+    }
+
+    /// Download RIB files by specifying an output folder and a date, or a time range.
     /// The downloaded files will then be parsed (existing files are not re-downloaded).
+    /// Files are stored in a sub-directory per day.
     #[derive(Debug, Args)]
     pub struct DownloadArgs {
         /// Download RIBs to this directory
         #[arg(short = 'p', long, default_value_t = String::from("./mrts/"))]
         pub ribs_path: String,
 
-        /// Download RIBs for yyyy-mm-dd
-        #[arg(short = 'y', long, default_value_t = String::from("2025-09-22"))]
-        pub ribs_ymd: String,
+        #[command(flatten)]
+        pub selection: RibSelectionArgs,
+
+        /// Number of RIBs to download in parallel
+        #[arg(short = 'c', long, default_value_t = 4)]
+        pub concurrency: usize,
+
+        /// Number of times to retry a failed download (with exponential backoff)
+        #[arg(short = 'r', long, default_value_t = 3)]
+        pub retries: u32,
     }
 
-    /// Parse RIB files which a;ready exist locally.
+    /// Find RIB files in a local RouteViews/RIS archive mirror, by date or time range.
+    /// Matching files are symlinked into the output folder, with the same names and
+    /// layout as downloaded files, then parsed.
+    #[derive(Debug, Args)]
+    pub struct MirrorArgs {
+        /// Root directory of the local archive mirror
+        #[arg(short = 'm', long)]
+        pub mirror_path: String,
+
+        /// Link RIBs into this directory
+        #[arg(short = 'p', long, default_value_t = String::from("./mrts/"))]
+        pub ribs_path: String,
+
+        #[command(flatten)]
+        pub selection: RibSelectionArgs,
+    }
+
+    /// Parse RIB files which a;ready exist locally, or stream them from http(s) URLs or stdin.
     #[derive(Debug, Args)]
     pub struct FileArgs {
-        /// Space seperated list of existing MRT files to parse
+        /// Space seperated list of existing MRT files, http(s) URLs, or "-" for stdin, to parse
         #[arg(short = 'f', long, value_delimiter = ' ', num_args = 1..)]
         pub rib_files: Vec<String>,
     }
@@ -29,15 +121,25 @@ pub mod cli_args {
         #[arg(short = 'i', long)]
         pub mrt_index: u32,
 
-        /// Existing MRT files to parse
+        /// Existing MRT file, http(s) URL, or "-" for stdin, to parse
         #[arg(short = 'f', long)]
         pub rib_file: String,
     }
 
+    /// Run the searches on a snapshot saved with --save, instead of parsing MRT files
+    #[derive(Debug, Args)]
+    pub struct LoadArgs {
+        /// Snapshot file to load
+        #[arg(short = 's', long)]
+        pub snapshot_file: String,
+    }
+
     #[derive(Subcommand, Debug)]
     pub enum RibsSource {
         Download(DownloadArgs),
         File(FileArgs),
+        Load(LoadArgs),
+        Mirror(MirrorArgs),
         Print(PrintArgs),
     }
 
@@ -55,6 +157,23 @@ pub mod cli_args {
         /// Number of threads to use for parsing MRT files
         #[arg(short, long, default_value_t = 1)]
         pub threads: u32,
+
+        /// Number of MRT records per batch, records in a batch are parsed in parallel
+        #[arg(short, long, default_value_t = 10000)]
+        pub batch_size: usize,
+
+        /// Save the parsed paths to this snapshot file, which can be searched again with "load"
+        #[arg(long)]
+        pub save: Option<String>,
+
+        /// Keep single-hop AS paths and origins with a single AS path, which are removed
+        /// after parsing by default, so the aggregate search sees every origin of a prefix
+        #[arg(long)]
+        pub no_filter: bool,
+
+        /// Local CAIDA as-rel file (serial-1 or serial-2), to check paths are valley-free
+        #[arg(long)]
+        pub as_rel: Option<String>,
     }
 
     impl CliArgs {
@@ -66,22 +185,84 @@ pub mod cli_args {
             }
         }
 
-        pub fn get_ribs_path(&self) -> &str {
-            if let RibsSource::Download(args) = &self.ribs_source {
-                args.ribs_path.as_str()
+        pub fn get_mirror_path(&self) -> &str {
+            if let RibsSource::Mirror(args) = &self.ribs_source {
+                args.mirror_path.as_str()
             } else {
                 panic!("No CLI option to unpack");
             }
         }
 
-        pub fn get_ribs_ymd(&self) -> &str {
+        pub fn get_ribs_path(&self) -> &str {
+            match &self.ribs_source {
+                RibsSource::Download(args) => args.ribs_path.as_str(),
+                RibsSource::Mirror(args) => args.ribs_path.as_str(),
+                _ => panic!("No CLI option to unpack"),
+            }
+        }
+
+        fn get_rib_selection(&self) -> &RibSelectionArgs {
+            match &self.ribs_source {
+                RibsSource::Download(args) => &args.selection,
+                RibsSource::Mirror(args) => &args.selection,
+                _ => panic!("No CLI option to unpack"),
+            }
+        }
+
+        pub fn get_download_config(&self) -> DownloadConfig {
             if let RibsSource::Download(args) = &self.ribs_source {
-                args.ribs_ymd.as_str()
+                DownloadConfig {
+                    concurrency: args.concurrency,
+                    retries: args.retries,
+                    ..DownloadConfig::default()
+                }
             } else {
                 panic!("No CLI option to unpack");
             }
         }
 
+        pub fn get_rib_filter(&self) -> RibFilter {
+            let args = self.get_rib_selection();
+            RibFilter {
+                include: args.include.clone(),
+                exclude: args.exclude.clone(),
+                project: args.project.clone(),
+            }
+        }
+
+        pub fn get_ribs_hours(&self) -> &Vec<u32> {
+            &self.get_rib_selection().hours
+        }
+
+        /// Return the start and end time to get RIBs for, either
+        /// from --from/--to or the whole day of --ribs-ymd.
+        /// Returns a usage error if --to is before --from or --ribs-ymd is invalid.
+        pub fn get_ribs_window(&self) -> Result<(NaiveDateTime, NaiveDateTime), clap::Error> {
+            let args = self.get_rib_selection();
+            if let Some(from) = args.from {
+                let to = args
+                    .to
+                    .unwrap_or(from.date().and_hms_opt(23, 59, 59).unwrap());
+                if to < from {
+                    return Err(CliArgs::command().error(
+                        ErrorKind::ArgumentConflict,
+                        format!("--to {} is before --from {}", to, from),
+                    ));
+                }
+                Ok((from, to))
+            } else {
+                let invalid = |e| {
+                    CliArgs::command().error(
+                        ErrorKind::ValueValidation,
+                        format!("Invalid --ribs-ymd {}: {}", args.ribs_ymd, e),
+                    )
+                };
+                let start = parse_start_time(&args.ribs_ymd).map_err(invalid)?;
+                let end = parse_end_time(&args.ribs_ymd).map_err(invalid)?;
+                Ok((start, end))
+            }
+        }
+
         pub fn get_rib_file(&self) -> &String {
             if let RibsSource::Print(args) = &self.ribs_source {
                 &args.rib_file
@@ -90,8 +271,19 @@ pub mod cli_args {
             }
         }
 
+        pub fn get_snapshot_file(&self) -> &str {
+            if let RibsSource::Load(args) = &self.ribs_source {
+                args.snapshot_file.as_str()
+            } else {
+                panic!("No CLI option to unpack");
+            }
+        }
+
         pub fn get_rib_files(&self) -> &Vec<String> {
             if let RibsSource::File(args) = &self.ribs_source {
+                if args.rib_files.iter().filter(|f| *f == STDIN).count() > 1 {
+                    panic!("stdin can only be read once");
+                }
                 &args.rib_files
             } else {
                 panic!("No CLI option to unpack");
@@ -102,6 +294,22 @@ pub mod cli_args {
             matches!(self.ribs_source, RibsSource::Download(_))
         }
 
+        pub fn list(&self) -> bool {
+            match &self.ribs_source {
+                RibsSource::Download(args) => args.selection.list,
+                RibsSource::Mirror(args) => args.selection.list,
+                _ => false,
+            }
+        }
+
+        pub fn load(&self) -> bool {
+            matches!(self.ribs_source, RibsSource::Load(_))
+        }
+
+        pub fn mirror(&self) -> bool {
+            matches!(self.ribs_source, RibsSource::Mirror(_))
+        }
+
         pub fn print(&self) -> bool {
             matches!(self.ribs_source, RibsSource::Print(_))
         }
@@ -110,4 +318,92 @@ pub mod cli_args {
     pub fn parse_cli_arg() -> CliArgs {
         CliArgs::parse()
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn get_time(value: &str) -> NaiveDateTime {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+        }
+
+        fn get_window(args: &[&str]) -> Result<(NaiveDateTime, NaiveDateTime), clap::Error> {
+            let mut argv = vec!["hidden-as-paths", "download"];
+            argv.extend_from_slice(args);
+            CliArgs::try_parse_from(argv)?.get_ribs_window()
+        }
+
+        #[test]
+        fn test_parse_start_time() {
+            assert_eq!(
+                parse_start_time("2025-09-22").unwrap(),
+                get_time("2025-09-22 00:00:00")
+            );
+            assert_eq!(
+                parse_start_time("2025-09-22T08:30").unwrap(),
+                get_time("2025-09-22 08:30:00")
+            );
+            assert!(parse_start_time("2025-09-22 08:30").is_err());
+            assert!(parse_start_time("20250922").is_err());
+        }
+
+        #[test]
+        fn test_parse_end_time() {
+            assert_eq!(
+                parse_end_time("2025-09-22").unwrap(),
+                get_time("2025-09-22 23:59:59")
+            );
+            assert_eq!(
+                parse_end_time("2025-09-22T08:30").unwrap(),
+                get_time("2025-09-22 08:30:00")
+            );
+            assert!(parse_end_time("2025-09-32").is_err());
+        }
+
+        #[test]
+        fn test_get_ribs_window() {
+            let window = |start, end| Ok((get_time(start), get_time(end)));
+            assert_eq!(
+                get_window(&[]).map_err(|e| e.kind()),
+                window("2025-09-22 00:00:00", "2025-09-22 23:59:59")
+            );
+            assert_eq!(
+                get_window(&["-y", "2025-09-21"]).map_err(|e| e.kind()),
+                window("2025-09-21 00:00:00", "2025-09-21 23:59:59")
+            );
+            assert_eq!(
+                get_window(&["--from", "2025-09-22T08:00"]).map_err(|e| e.kind()),
+                window("2025-09-22 08:00:00", "2025-09-22 23:59:59")
+            );
+            assert_eq!(
+                get_window(&["--from", "2025-09-20", "--to", "2025-09-22"]).map_err(|e| e.kind()),
+                window("2025-09-20 00:00:00", "2025-09-22 23:59:59")
+            );
+            assert_eq!(
+                get_window(&["--from", "2025-09-22T08:00", "--to", "2025-09-22T08:00"])
+                    .map_err(|e| e.kind()),
+                window("2025-09-22 08:00:00", "2025-09-22 08:00:00")
+            );
+        }
+
+        #[test]
+        fn test_get_ribs_window_errors() {
+            for (args, kind) in [
+                (
+                    ["--from", "2025-09-22T08:00", "--to", "2025-09-22T07:59"].as_slice(),
+                    ErrorKind::ArgumentConflict,
+                ),
+                (
+                    &["--from", "2025-09-22", "--to", "2025-09-21"],
+                    ErrorKind::ArgumentConflict,
+                ),
+                (&["-y", "2025-09-32"], ErrorKind::ValueValidation),
+                (&["--from", "22/09/2025"], ErrorKind::ValueValidation),
+                (&["--to", "2025-09-22"], ErrorKind::MissingRequiredArgument),
+                (&["--hours", "24"], ErrorKind::ValueValidation),
+            ] {
+                assert_eq!(get_window(args).unwrap_err().kind(), kind, "{:?}", args);
+            }
+        }
+    }
 }
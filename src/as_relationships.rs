@@ -0,0 +1,118 @@
+pub mod as_rel {
+    use crate::reader::mrt_reader::get_decompressed_reader;
+    use bgpkit_parser::models::Asn;
+    use log::info;
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Read};
+
+    /// The relationship of one ASN to another
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Relationship {
+        /// The first ASN is a provider of the second
+        ProviderToCustomer,
+        /// The first ASN is a customer of the second
+        CustomerToProvider,
+        PeerToPeer,
+    }
+
+    /// AS relationships from a CAIDA as-rel file, stored in both directions
+    #[derive(Debug, Default)]
+    pub struct AsRelationships {
+        relationships: HashMap<(u32, u32), Relationship>,
+    }
+
+    impl AsRelationships {
+        /// Parse the CAIDA serial-1 ("<provider>|<customer>|-1" or "<peer>|<peer>|0")
+        /// or serial-2 (with an extra "|<source>" column) format, "#" lines are comments
+        pub fn from_reader<R: Read>(reader: R) -> Self {
+            let mut as_relationships = AsRelationships::default();
+            for (line_no, line) in BufReader::new(reader).lines().enumerate() {
+                let line =
+                    line.unwrap_or_else(|e| panic!("Unable to read AS relationships: {}", e));
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let fields: Vec<&str> = line.split('|').collect();
+                if fields.len() < 3 {
+                    panic!("Invalid AS relationship on line {}: {}", line_no + 1, line);
+                }
+                let parse_asn = |field: &str| {
+                    field.parse::<u32>().unwrap_or_else(|e| {
+                        panic!("Invalid ASN on line {}: {}: {}", line_no + 1, line, e)
+                    })
+                };
+                let (a, b) = (parse_asn(fields[0]), parse_asn(fields[1]));
+                match fields[2] {
+                    "-1" => as_relationships.add(a, b, Relationship::ProviderToCustomer),
+                    "0" => as_relationships.add(a, b, Relationship::PeerToPeer),
+                    _ => panic!("Invalid relationship on line {}: {}", line_no + 1, line),
+                }
+            }
+            as_relationships
+        }
+
+        /// Load a local as-rel file, which may be bzip2 or gzip compressed
+        pub fn load(filename: &str) -> Self {
+            info!("Loading AS relationships from {}", filename);
+            let file = File::open(filename)
+                .unwrap_or_else(|e| panic!("Unable to open {}: {}", filename, e));
+            let as_relationships = Self::from_reader(get_decompressed_reader(file));
+            info!("Loaded {} AS relationships", as_relationships.len());
+            as_relationships
+        }
+
+        fn add(&mut self, a: u32, b: u32, relationship: Relationship) {
+            let reverse = match relationship {
+                Relationship::ProviderToCustomer => Relationship::CustomerToProvider,
+                Relationship::CustomerToProvider => Relationship::ProviderToCustomer,
+                Relationship::PeerToPeer => Relationship::PeerToPeer,
+            };
+            self.relationships.insert((a, b), relationship);
+            self.relationships.insert((b, a), reverse);
+        }
+
+        /// The relationship of a to b
+        pub fn get_relationship(&self, a: &Asn, b: &Asn) -> Option<Relationship> {
+            self.relationships.get(&(a.to_u32(), b.to_u32())).copied()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Number of AS pairs with a known relationship
+        pub fn len(&self) -> usize {
+            self.relationships.len() / 2
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_from_reader() {
+            let as_rel = "# source:topology|BGP\n1|2|-1\n2|3|0|bgp\n\n";
+            let as_relationships = AsRelationships::from_reader(as_rel.as_bytes());
+            assert_eq!(as_relationships.len(), 2);
+
+            let asn = |asn: u32| Asn::new_32bit(asn);
+            assert_eq!(
+                as_relationships.get_relationship(&asn(1), &asn(2)),
+                Some(Relationship::ProviderToCustomer)
+            );
+            assert_eq!(
+                as_relationships.get_relationship(&asn(2), &asn(1)),
+                Some(Relationship::CustomerToProvider)
+            );
+            assert_eq!(
+                as_relationships.get_relationship(&asn(3), &asn(2)),
+                Some(Relationship::PeerToPeer)
+            );
+            assert_eq!(as_relationships.get_relationship(&asn(1), &asn(3)), None);
+        }
+    }
+}
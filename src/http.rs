@@ -1,45 +1,369 @@
 pub mod http_client {
-    use log::{debug, info};
+    use log::{debug, error, info, warn};
+    use rayon::ThreadPoolBuilder;
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+    use reqwest::StatusCode;
     use reqwest::blocking::Client;
-    use std::fs::File;
-    use std::io::Write;
-    use std::path::Path;
+    use reqwest::header::RANGE;
+    use std::fs::{self, File, OpenOptions};
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::thread;
     use std::time::Duration;
 
-    pub fn download_file(url: &str, dest: &Path) {
-        if dest.exists() {
-            debug!(
-                "Not GETting URL {}, output file already exists {}",
-                url,
-                dest.to_str().unwrap(),
-            );
-            return;
+    /// A file to download, with the expected size if the broker provided one
+    #[derive(Clone, Debug)]
+    pub struct Download {
+        pub url: String,
+        pub dest: PathBuf,
+        pub size: Option<u64>,
+    }
+
+    /// How many downloads run at once, and how failed downloads are retried
+    #[derive(Clone, Debug)]
+    pub struct DownloadConfig {
+        pub concurrency: usize,
+        pub retries: u32,
+        pub backoff: Duration,
+        pub timeout: Duration,
+    }
+
+    impl Default for DownloadConfig {
+        fn default() -> Self {
+            DownloadConfig {
+                concurrency: 4,
+                retries: 3,
+                backoff: Duration::from_secs(2),
+                timeout: Duration::from_secs(300), // Increase default timeout
+            }
         }
+    }
 
-        info!("GET'ing URL {}", url);
+    /// Return the path which a download is streamed to, before being renamed into place
+    fn get_part_path(dest: &Path) -> PathBuf {
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        PathBuf::from(part)
+    }
+
+    fn get_file_size(path: &Path) -> Option<u64> {
+        fs::metadata(path).ok().map(|m| m.len())
+    }
+
+    /// Download many files, with a bounded number of downloads in parallel.
+    /// Returns the downloads which failed after all retries.
+    pub fn download_files(downloads: &[Download], config: &DownloadConfig) -> Vec<Download> {
+        info!(
+            "Downloading {} files, {} at a time",
+            downloads.len(),
+            config.concurrency
+        );
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(300)) // Increase default timeout
+        let client = Client::builder().timeout(config.timeout).build().unwrap();
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.concurrency.max(1))
             .build()
             .unwrap();
 
-        let response = client
-            .get(url)
-            .send()
-            .map_err(|e| format!("HTTP GET failed: {}", e))
-            .unwrap();
+        let failed: Vec<Download> = pool.install(|| {
+            downloads
+                .par_iter()
+                .filter_map(|download| {
+                    match download_file_with_retries(&client, download, config) {
+                        Ok(()) => None,
+                        Err(e) => {
+                            error!("Failed to download {}: {}", download.url, e);
+                            Some(download.clone())
+                        }
+                    }
+                })
+                .collect()
+        });
 
-        let content = response
-            .bytes()
-            .map_err(|e| format!("Failed to read response bytes: {}", e))
-            .unwrap();
+        info!(
+            "Downloaded {} files, {} failed",
+            downloads.len() - failed.len(),
+            failed.len()
+        );
+        failed
+    }
 
-        File::create(dest)
-            .map_err(|e| format!("Failed to create file: {}", e))
-            .unwrap()
-            .write_all(&content)
-            .unwrap();
+    fn download_file_with_retries(
+        client: &Client,
+        download: &Download,
+        config: &DownloadConfig,
+    ) -> Result<(), String> {
+        let mut attempt = 0;
+        loop {
+            match download_file(client, download) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < config.retries => {
+                    let delay = config.backoff * 2_u32.pow(attempt);
+                    warn!(
+                        "Download of {} failed (attempt {}), retrying in {:?}: {}",
+                        download.url,
+                        attempt + 1,
+                        delay,
+                        e
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Check a downloaded file against the expected size (if known).
+    /// Only the size is checked: bgpkit-broker 0.10's BrokerItem has an exact_size
+    /// but no checksum, so a corrupt file of the right size isn't detected here.
+    fn verify_size(path: &Path, size: Option<u64>) -> Result<(), String> {
+        let Some(expected) = size else {
+            return Ok(());
+        };
+        let actual = get_file_size(path).unwrap_or(0);
+        if actual != expected {
+            return Err(format!(
+                "Size mismatch for {}, expected {} bytes, got {}",
+                path.to_str().unwrap(),
+                expected,
+                actual
+            ));
+        }
+        Ok(())
+    }
+
+    /// Stream a URL to a .part file, resuming a previous partial download with an
+    /// HTTP Range request, then rename the .part file into place once it is complete.
+    pub fn download_file(client: &Client, download: &Download) -> Result<(), String> {
+        let dest = download.dest.as_path();
+        let url = download.url.as_str();
+
+        if dest.exists() {
+            if verify_size(dest, download.size).is_ok() {
+                debug!(
+                    "Not GETting URL {}, output file already exists {}",
+                    url,
+                    dest.to_str().unwrap(),
+                );
+                return Ok(());
+            }
+            warn!(
+                "Existing file {} is incomplete, downloading it again",
+                dest.to_str().unwrap()
+            );
+            fs::rename(dest, get_part_path(dest))
+                .map_err(|e| format!("Failed to move incomplete file: {}", e))?;
+        }
+
+        let part = get_part_path(dest);
+        let offset = get_file_size(&part).unwrap_or(0);
+
+        if download.size.is_some_and(|size| offset >= size) {
+            debug!(
+                "Partial file {} is already complete",
+                part.to_str().unwrap()
+            );
+        } else {
+            let mut request = client.get(url);
+            if offset > 0 {
+                info!("GET'ing URL {} from byte {}", url, offset);
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            } else {
+                info!("GET'ing URL {}", url);
+            }
+
+            let mut response = request
+                .send()
+                .map_err(|e| format!("HTTP GET failed: {}", e))?;
+
+            let mut file = match response.status() {
+                StatusCode::PARTIAL_CONTENT => OpenOptions::new()
+                    .append(true)
+                    .open(&part)
+                    .map_err(|e| format!("Failed to open file: {}", e))?,
+                StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                    // The server has nothing after offset, so the partial file should be complete
+                    verify_size(&part, download.size)?;
+                    return finish_download(&part, dest);
+                }
+                status if status.is_success() => {
+                    // Server ignored the range request or this is a fresh download
+                    File::create(&part).map_err(|e| format!("Failed to create file: {}", e))?
+                }
+                status => return Err(format!("HTTP GET returned {}", status)),
+            };
 
+            io::copy(&mut response, &mut file)
+                .map_err(|e| format!("Failed to read response bytes: {}", e))?;
+        }
+
+        if let Err(e) = verify_size(&part, download.size) {
+            // A larger file than expected can't be fixed by resuming
+            if get_file_size(&part).unwrap_or(0) > download.size.unwrap_or(0) {
+                fs::remove_file(&part).map_err(|e| format!("Failed to remove file: {}", e))?;
+            }
+            return Err(e);
+        }
+
+        finish_download(&part, dest)
+    }
+
+    fn finish_download(part: &Path, dest: &Path) -> Result<(), String> {
+        fs::rename(part, dest).map_err(|e| format!("Failed to rename file: {}", e))?;
         info!("Wrote to file {}", dest.to_str().unwrap());
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        /// A minimal HTTP server which serves body for any path, honours
+        /// "Range: bytes=N-" and fails the first `failures` requests with a 503.
+        fn serve(body: Vec<u8>, failures: u32) -> (String, Arc<AtomicU32>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/rib.bz2", listener.local_addr().unwrap());
+            let requests = Arc::new(AtomicU32::new(0));
+            let counter = requests.clone();
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut offset = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some(range) = line.to_lowercase().strip_prefix("range: bytes=") {
+                            offset = range.trim().trim_end_matches('-').parse().unwrap();
+                        }
+                    }
+
+                    let count = counter.fetch_add(1, Ordering::SeqCst);
+                    let response = if count < failures {
+                        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n".to_vec()
+                    } else if offset > 0 {
+                        let mut r = format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\r\n",
+                            body.len() - offset
+                        )
+                        .into_bytes();
+                        r.extend_from_slice(&body[offset..]);
+                        r
+                    } else {
+                        let mut r =
+                            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len())
+                                .into_bytes();
+                        r.extend_from_slice(&body);
+                        r
+                    };
+                    stream.write_all(&response).unwrap();
+                }
+            });
+
+            (url, requests)
+        }
+
+        fn get_test_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!(
+                "hidden-as-paths-http-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn get_test_config() -> DownloadConfig {
+            DownloadConfig {
+                concurrency: 2,
+                retries: 2,
+                backoff: Duration::from_millis(1),
+                timeout: Duration::from_secs(5),
+            }
+        }
+
+        #[test]
+        fn test_download_and_retry() {
+            let body = b"mrt data".repeat(1000);
+            let (url, requests) = serve(body.clone(), 1);
+            let dest = get_test_dir("retry").join("rib.bz2");
+            let download = Download {
+                url,
+                dest: dest.clone(),
+                size: Some(body.len() as u64),
+            };
+
+            let failed = download_files(&[download], &get_test_config());
+            assert!(failed.is_empty());
+            assert_eq!(fs::read(&dest).unwrap(), body);
+            assert!(!get_part_path(&dest).exists());
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+        }
+
+        #[test]
+        fn test_download_resumes_part_file() {
+            let body = b"0123456789".repeat(100);
+            let (url, _) = serve(body.clone(), 0);
+            let dest = get_test_dir("resume").join("rib.bz2");
+            fs::write(get_part_path(&dest), &body[..250]).unwrap();
+
+            let download = Download {
+                url,
+                dest: dest.clone(),
+                size: Some(body.len() as u64),
+            };
+            let client = Client::builder().build().unwrap();
+            download_file(&client, &download).unwrap();
+            assert_eq!(fs::read(&dest).unwrap(), body);
+        }
+
+        #[test]
+        fn test_truncated_file_is_redownloaded() {
+            let body = b"abcdef".repeat(100);
+            let (url, requests) = serve(body.clone(), 0);
+            let dest = get_test_dir("truncated").join("rib.bz2");
+            fs::write(&dest, &body[..10]).unwrap();
+
+            let download = Download {
+                url,
+                dest: dest.clone(),
+                size: Some(body.len() as u64),
+            };
+            let failed = download_files(std::slice::from_ref(&download), &get_test_config());
+            assert!(failed.is_empty());
+            assert_eq!(fs::read(&dest).unwrap(), body);
+
+            // A complete file isn't downloaded again
+            let failed = download_files(&[download], &get_test_config());
+            assert!(failed.is_empty());
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+        }
+
+        #[test]
+        fn test_download_fails_after_retries() {
+            let (url, requests) = serve(Vec::from(*b"data"), 100);
+            let dest = get_test_dir("fail").join("rib.bz2");
+            let download = Download {
+                url,
+                dest: dest.clone(),
+                size: None,
+            };
+
+            let failed = download_files(&[download], &get_test_config());
+            assert_eq!(failed.len(), 1);
+            assert!(!dest.exists());
+            assert_eq!(requests.load(Ordering::SeqCst), 3);
+        }
     }
 }
@@ -1,3 +1,4 @@
+use crate::mrt_interner::interner::{AS_PATHS, FILENAMES, PEERS, RAW_AS_PATHS};
 use std::io::Write;
 
 pub fn setup_logging(level: &str) {
@@ -15,3 +16,37 @@ pub fn setup_logging(level: &str) {
         })
         .init();
 }
+
+/// Return a "VmRSS"/"VmHWM" style value from /proc/self/status, in kB (Linux only)
+fn get_proc_status_kb(field: &str) -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with(field))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Log the current and peak resident memory, and the size of the interning tables
+pub fn log_memory_usage(stage: &str) {
+    let rss = get_proc_status_kb("VmRSS:");
+    let peak = get_proc_status_kb("VmHWM:");
+    match (rss, peak) {
+        (Some(rss), Some(peak)) => log::info!(
+            "Memory usage {}: {} MiB resident, {} MiB peak",
+            stage,
+            rss / 1024,
+            peak / 1024
+        ),
+        _ => log::info!("Memory usage {}: not available on this platform", stage),
+    }
+    log::info!(
+        "Interned {} AS paths, {} peers, {} filenames, {} raw AS_PATH/AS4_PATH pairs",
+        AS_PATHS.len(),
+        PEERS.len(),
+        FILENAMES.len(),
+        RAW_AS_PATHS.len()
+    );
+}
@@ -9,11 +9,17 @@ static GLOBAL: Jemalloc = Jemalloc;
 use crate::ribs::rib_getter::RibFile;
 
 pub mod args;
+pub mod as_relationships;
 pub mod http;
 pub mod logging;
+pub mod mirror;
+pub mod mrt_aggregator;
 pub mod mrt_as_path;
+pub mod mrt_as_path_segments;
 pub mod mrt_asn;
 pub mod mrt_community;
+pub mod mrt_divergent_paths;
+pub mod mrt_interner;
 pub mod mrt_ip_addr;
 pub mod mrt_ip_net;
 pub mod mrt_large_community;
@@ -23,8 +29,17 @@ pub mod mrt_peer;
 pub mod mrt_route;
 pub mod parse;
 pub mod print;
+pub mod reader;
+pub mod report;
 pub mod ribs;
 pub mod search;
+pub mod search_aggregates;
+pub mod search_aggregators;
+pub mod search_communities;
+pub mod search_peers;
+pub mod search_special_asns;
+pub mod search_valley_free;
+pub mod snapshot;
 
 fn main() {
     let args = args::cli_args::parse_cli_arg();
@@ -39,18 +54,71 @@ fn main() {
         return;
     }
 
-    let rib_files: Vec<RibFile> = if args.download() {
-        ribs::rib_getter::download_ribs_for_day(args.get_ribs_ymd(), args.get_ribs_path())
+    let as_relationships = args
+        .as_rel
+        .as_ref()
+        .map(|filename| as_relationships::as_rel::AsRelationships::load(filename));
+
+    if args.load() {
+        let path_data = snapshot::path_snapshot::load_path_data(args.get_snapshot_file())
+            .unwrap_or_else(|e| panic!("Unable to load snapshot: {}", e));
+        logging::log_memory_usage("after loading");
+        search::path_search::find_paths(&path_data, as_relationships.as_ref());
+        return;
+    }
+
+    let rib_files: Vec<RibFile> = if args.download() || args.mirror() {
+        let (start, end) = args.get_ribs_window().unwrap_or_else(|e| e.exit());
+        if args.mirror() {
+            mirror::rib_mirror::find_ribs_for_range(
+                args.get_mirror_path(),
+                &start,
+                &end,
+                args.get_ribs_hours(),
+                &args.get_rib_filter(),
+                args.get_ribs_path(),
+            )
+        } else {
+            ribs::rib_getter::get_rib_list_for_range(
+                &start,
+                &end,
+                args.get_ribs_hours(),
+                &args.get_rib_filter(),
+                args.get_ribs_path(),
+            )
+        }
     } else {
         args.get_rib_files()
             .iter()
-            .map(|filename| RibFile {
-                url: String::new(),
-                filename: filename.clone(),
-            })
+            .map(|filename| RibFile::from_filename(filename))
             .collect()
     };
 
-    let path_data = parse::rib_parser::get_path_data(&rib_files, &args.threads);
-    search::path_search::find_paths(&path_data);
+    if args.list() {
+        ribs::rib_getter::print_rib_list(&rib_files);
+        return;
+    }
+
+    let rib_files = if args.download() {
+        ribs::rib_getter::download_ribs(rib_files, &args.get_download_config())
+    } else if args.mirror() {
+        mirror::rib_mirror::link_ribs_to_dir(&rib_files);
+        rib_files
+    } else {
+        rib_files
+    };
+
+    let path_data = parse::rib_parser::get_path_data(
+        &rib_files,
+        &args.threads,
+        &args.batch_size,
+        !args.no_filter,
+    );
+    logging::log_memory_usage("after parsing");
+    if let Some(snapshot_file) = &args.save {
+        let source_files = rib_files.iter().map(|f| f.filename.clone()).collect();
+        snapshot::path_snapshot::save_path_data(&path_data, source_files, snapshot_file)
+            .unwrap_or_else(|e| panic!("Unable to save snapshot: {}", e));
+    }
+    search::path_search::find_paths(&path_data, as_relationships.as_ref());
 }
@@ -0,0 +1,253 @@
+pub mod rib_mirror {
+    use crate::ribs::rib_getter::{
+        RibFile, RibFilter, get_rib_filename, get_rib_timestamp, get_snapshot, group_by_snapshot,
+        is_snapshot_hour,
+    };
+    use chrono::{Datelike, NaiveDate, NaiveDateTime};
+    use log::{debug, info, warn};
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    /// Return the RIB directory for a collector and month in a local mirror,
+    /// using the standard archive layouts:
+    /// RIS:                      <mirror>/rrc00/2025.09/bview.20250922.0000.gz
+    /// RouteViews (route-views2): <mirror>/bgpdata/2025.09/RIBS/rib.20250922.0000.bz2
+    /// RouteViews (others):       <mirror>/route-views.amsix/bgpdata/2025.09/RIBS/rib.20250922.0000.bz2
+    fn get_month_dir(mirror: &Path, collector_id: &str, month: &NaiveDate) -> PathBuf {
+        let month = month.format("%Y.%m").to_string();
+        if collector_id.starts_with("rrc") {
+            mirror.join(collector_id).join(month)
+        } else if collector_id == "route-views2" && mirror.join("bgpdata").exists() {
+            mirror.join("bgpdata").join(month).join("RIBS")
+        } else {
+            mirror
+                .join(collector_id)
+                .join("bgpdata")
+                .join(month)
+                .join("RIBS")
+        }
+    }
+
+    /// Return the collector IDs in a local mirror, from the top level directory names
+    fn get_collectors(mirror: &Path) -> Vec<String> {
+        let entries = fs::read_dir(mirror)
+            .unwrap_or_else(|e| panic!("Unable to read mirror {}: {}", mirror.display(), e));
+
+        let mut collectors = Vec::<String>::new();
+        for entry in entries.flatten() {
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "bgpdata" {
+                collectors.push(String::from("route-views2"));
+            } else if name.starts_with("rrc") || name.starts_with("route-views") {
+                collectors.push(name);
+            }
+        }
+        collectors.sort();
+        collectors.dedup();
+        collectors
+    }
+
+    /// Return the first day of every month from start to end (inclusive)
+    fn get_months(start: &NaiveDateTime, end: &NaiveDateTime) -> Vec<NaiveDate> {
+        let mut months = Vec::<NaiveDate>::new();
+        let mut month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1).unwrap();
+        while month <= end.date() {
+            months.push(month);
+            month = month.checked_add_months(chrono::Months::new(1)).unwrap();
+        }
+        months
+    }
+
+    /// Return a list of RIBs available in a local mirror for a time range, only keeping
+    /// snapshots which were taken at one of the specified hours. Filenames are the same
+    /// as for RIBs downloaded via the broker.
+    pub fn find_ribs_for_range(
+        mirror: &str,
+        start: &NaiveDateTime,
+        end: &NaiveDateTime,
+        hours: &[u32],
+        filter: &RibFilter,
+        dir: &str,
+    ) -> Vec<RibFile> {
+        info!(
+            "Searching mirror {} for MRT RIBs from {} to {} (hours {:?})",
+            mirror, start, end, hours
+        );
+        let mirror = Path::new(mirror);
+        let mut rib_files = Vec::<RibFile>::new();
+
+        for collector_id in get_collectors(mirror) {
+            if !filter.matches(&collector_id) {
+                debug!("Skipping filtered collector {}", collector_id);
+                continue;
+            }
+
+            for month in get_months(start, end) {
+                let month_dir = get_month_dir(mirror, &collector_id, &month);
+                let Ok(entries) = fs::read_dir(&month_dir) else {
+                    debug!("No RIBs in {}", month_dir.display());
+                    continue;
+                };
+
+                for entry in entries.flatten() {
+                    let basename = entry.file_name().to_string_lossy().to_string();
+                    let Some(timestamp) = get_rib_timestamp(&basename) else {
+                        continue;
+                    };
+                    if timestamp < *start
+                        || timestamp > *end
+                        || !is_snapshot_hour(&timestamp, hours)
+                    {
+                        continue;
+                    }
+
+                    rib_files.push(RibFile {
+                        url: entry.path().to_str().unwrap().to_string(),
+                        filename: get_rib_filename(dir, &collector_id, &timestamp, &basename),
+                        collector_id: collector_id.clone(),
+                        snapshot: get_snapshot(&timestamp),
+                        size: entry.metadata().ok().map(|m| m.len()),
+                    });
+                }
+            }
+        }
+
+        rib_files.sort_by(|a, b| (&a.snapshot, &a.filename).cmp(&(&b.snapshot, &b.filename)));
+        info!("Found {} MRT files in mirror", rib_files.len());
+        for (snapshot, snapshot_files) in group_by_snapshot(&rib_files) {
+            info!("Snapshot {}: {} RIB files", snapshot, snapshot_files.len());
+        }
+        rib_files
+    }
+
+    #[cfg(unix)]
+    fn link_file(source: &Path, dest: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(source, dest)
+    }
+
+    /// Symlinks need extra privileges on Windows, so hard link the file instead,
+    /// or copy it if the mirror is on another volume
+    #[cfg(not(unix))]
+    fn link_file(source: &Path, dest: &Path) -> io::Result<()> {
+        fs::hard_link(source, dest).or_else(|_| fs::copy(source, dest).map(|_| ()))
+    }
+
+    /// Link the mirror files into place, existing files are left as they are.
+    /// A dangling symlink (e.g. from a mirror which has moved) is replaced.
+    pub fn link_ribs_to_dir(rib_files: &Vec<RibFile>) {
+        for rib_file in rib_files {
+            let dest = Path::new(&rib_file.filename);
+            if dest.exists() {
+                debug!("Not linking {}, file already exists", rib_file.filename);
+                continue;
+            }
+            // exists() follows symlinks, so is false for a dangling link
+            if fs::symlink_metadata(dest).is_ok() {
+                warn!("Replacing dangling link {}", rib_file.filename);
+                fs::remove_file(dest)
+                    .unwrap_or_else(|e| panic!("Unable to remove {}: {}", rib_file.filename, e));
+            }
+
+            let mrt_path = dest.parent().unwrap();
+            if !mrt_path.exists() {
+                debug!("Creating path: {}", mrt_path.display());
+                fs::create_dir_all(mrt_path)
+                    .unwrap_or_else(|e| panic!("Unable to create {}: {}", mrt_path.display(), e));
+            }
+
+            let source = fs::canonicalize(&rib_file.url)
+                .unwrap_or_else(|e| panic!("Unable to resolve {}: {}", rib_file.url, e));
+            link_file(&source, dest).unwrap_or_else(|e| {
+                panic!(
+                    "Unable to link {} to {}: {}",
+                    rib_file.url, rib_file.filename, e
+                )
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_find_ribs_for_range() {
+            let mirror =
+                std::env::temp_dir().join(format!("hidden-as-paths-mirror-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&mirror);
+            for path in [
+                "rrc00/2025.09/bview.20250922.0000.gz",
+                "rrc00/2025.09/bview.20250922.0800.gz",
+                "rrc00/2025.09/updates.20250922.0000.gz",
+                "bgpdata/2025.09/RIBS/rib.20250922.0000.bz2",
+                "route-views.amsix/bgpdata/2025.09/RIBS/rib.20250922.0000.bz2",
+                "route-views.amsix/bgpdata/2025.09/RIBS/rib.20250923.0000.bz2",
+            ] {
+                let path = mirror.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, b"").unwrap();
+            }
+
+            let start = NaiveDateTime::parse_from_str("202509220000", "%Y%m%d%H%M").unwrap();
+            let end = NaiveDateTime::parse_from_str("202509222359", "%Y%m%d%H%M").unwrap();
+            let rib_files = find_ribs_for_range(
+                mirror.to_str().unwrap(),
+                &start,
+                &end,
+                &[0],
+                &RibFilter::default(),
+                "./mrts",
+            );
+
+            let filenames: Vec<&str> = rib_files.iter().map(|r| r.filename.as_str()).collect();
+            assert_eq!(
+                filenames,
+                Vec::from([
+                    "./mrts/2025-09-22/ris.rrc00.bview.20250922.0000.gz",
+                    "./mrts/2025-09-22/route-views.amsix.rib.20250922.0000.bz2",
+                    "./mrts/2025-09-22/route-views2.rib.20250922.0000.bz2",
+                ])
+            );
+            assert!(rib_files.iter().all(|r| r.snapshot == "2025-09-22 00:00"));
+
+            fs::remove_dir_all(&mirror).unwrap();
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_link_ribs_to_dir_replaces_dangling_link() {
+            let dir =
+                std::env::temp_dir().join(format!("hidden-as-paths-link-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            let source = dir.join("mirror/bview.20250922.0000.gz");
+            let existing = dir.join("mirror/bview.20250922.0800.gz");
+            fs::create_dir_all(source.parent().unwrap()).unwrap();
+            fs::write(&source, b"mirror").unwrap();
+            fs::write(&existing, b"mirror").unwrap();
+
+            let dangling = dir.join("mrts/ris.rrc00.bview.20250922.0000.gz");
+            let local = dir.join("mrts/ris.rrc00.bview.20250922.0800.gz");
+            fs::create_dir_all(dangling.parent().unwrap()).unwrap();
+            link_file(&dir.join("moved/bview.20250922.0000.gz"), &dangling).unwrap();
+            fs::write(&local, b"local").unwrap();
+            assert!(!dangling.exists());
+
+            let rib_files: Vec<RibFile> = [(&source, &dangling), (&existing, &local)]
+                .into_iter()
+                .map(|(url, filename)| RibFile {
+                    url: url.to_str().unwrap().to_string(),
+                    ..RibFile::from_filename(filename.to_str().unwrap())
+                })
+                .collect();
+            link_ribs_to_dir(&rib_files);
+            assert_eq!(fs::read(&dangling).unwrap(), b"mirror");
+            assert_eq!(fs::read(&local).unwrap(), b"local");
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
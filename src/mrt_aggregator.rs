@@ -0,0 +1,47 @@
+pub mod aggregator {
+    use bgpkit_parser::models::Asn;
+    use std::net::Ipv4Addr;
+
+    /// The AGGREGATOR attribute, with AS4_AGGREGATOR already applied (RFC 6793)
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct Aggregator {
+        pub asn: Asn,
+        /// BGP identifier of the router which performed the aggregation
+        pub ip: Ipv4Addr,
+    }
+
+    impl Aggregator {
+        /// Use AS4_AGGREGATOR only when AGGREGATOR holds AS_TRANS, as per RFC 6793
+        /// section 4.2.3, otherwise AS4_AGGREGATOR is ignored.
+        pub fn new(
+            aggregator: Option<(Asn, Ipv4Addr)>,
+            as4_aggregator: Option<(Asn, Ipv4Addr)>,
+        ) -> Option<Self> {
+            let (asn, ip) = match (aggregator, as4_aggregator) {
+                (Some((asn, _)), Some(as4)) if asn == Asn::TRANSITION => as4,
+                (Some(aggregator), _) => aggregator,
+                (None, as4_aggregator) => as4_aggregator?,
+            };
+            Some(Aggregator { asn, ip })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_as4_aggregator() {
+            let ip = Ipv4Addr::new(192, 0, 2, 1);
+            let as4 = Some((Asn::new_32bit(196608), ip));
+
+            let aggregator = Aggregator::new(Some((Asn::TRANSITION, ip)), as4).unwrap();
+            assert_eq!(aggregator.asn, Asn::new_32bit(196608));
+
+            let aggregator = Aggregator::new(Some((Asn::new_16bit(1), ip)), as4).unwrap();
+            assert_eq!(aggregator.asn, Asn::new_16bit(1));
+
+            assert!(Aggregator::new(None, None).is_none());
+        }
+    }
+}
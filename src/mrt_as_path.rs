@@ -1,15 +1,18 @@
 pub mod as_path {
     use crate::mrt_asn::asn::Testing;
+    use crate::mrt_interner::interner::AsPathId;
     use crate::mrt_route::route::Route;
     use bgpkit_parser::models::Asn;
+    use indexmap::IndexSet;
     use log::debug;
     use std::hash::Hash;
 
-    /// A deduped AS path which stores one or more routes
+    /// A deduped AS path which stores one or more unique routes, in the order they were added
     #[derive(Debug, Clone, Eq)]
     pub struct AsPath {
-        as_path: Vec<Asn>,
-        routes: Vec<Route>,
+        /// Interned, as many routes (across many origins) share the same path
+        as_path: AsPathId,
+        routes: IndexSet<Route>,
     }
 
     impl PartialEq for AsPath {
@@ -18,10 +21,10 @@ pub mod as_path {
         }
     }
 
+    /// Routes are compared as a set by Eq, so they aren't hashed
     impl Hash for AsPath {
         fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
             self.as_path.hash(state);
-            self.routes.hash(state);
         }
     }
 
@@ -29,8 +32,8 @@ pub mod as_path {
         pub fn new(mut as_path: Vec<Asn>) -> Self {
             as_path.dedup();
             AsPath {
-                as_path,
-                routes: Vec::<Route>::new(),
+                as_path: AsPathId::new(as_path),
+                routes: IndexSet::<Route>::new(),
             }
         }
 
@@ -44,18 +47,19 @@ pub mod as_path {
         }
 
         pub fn add_route(&mut self, route: Route) {
-            if self.has_route(&route) {
-                return;
-            };
-            self.routes.push(route);
+            self.routes.insert(route);
         }
 
         pub fn get_as_path(&self) -> &Vec<Asn> {
-            &self.as_path
+            self.as_path.get()
+        }
+
+        pub fn get_as_path_id(&self) -> AsPathId {
+            self.as_path
         }
 
         fn get_asns(&self) -> &Vec<Asn> {
-            &self.as_path
+            self.as_path.get()
         }
 
         /// The same ASN appears somewhere in both AS Paths (not the final, origin, ASN),
@@ -64,11 +68,24 @@ pub mod as_path {
         /// a = [1, 2, 3]
         /// b = [4, 2, 5, 3]
         ///         ^  ^
+        /// AS_TRANS is a placeholder rather than a real ASN, so it's never a shared ASN.
         pub fn has_divergence_with(&self, other: &AsPath) -> bool {
+            self.get_divergence_with(other).is_some()
+        }
+
+        /// Return the first shared ASN (see has_divergence_with()) and the sub-paths
+        /// of self and other from that ASN up to and including the origin.
+        pub fn get_divergence_with<'a>(
+            &'a self,
+            other: &'a AsPath,
+        ) -> Option<(Asn, &'a [Asn], &'a [Asn])> {
             let a_path = self.get_asns().split_last().unwrap().1;
             let b_path = other.get_asns().split_last().unwrap().1;
 
             for a_asn in a_path {
+                if *a_asn == Asn::TRANSITION {
+                    continue;
+                }
                 let a_pos = a_path.iter().position(|x| x == a_asn).unwrap();
                 let b_pos = b_path.iter().position(|x| x == a_asn);
 
@@ -76,10 +93,44 @@ pub mod as_path {
                     && a_path[a_pos..] != b_path[b_pos..]
                     && (a_path.len() - a_pos != b_path.len() - b_pos)
                 {
-                    return true;
+                    return Some((
+                        *a_asn,
+                        &self.get_asns()[a_pos..],
+                        &other.get_asns()[b_pos..],
+                    ));
+                }
+            }
+            None
+        }
+
+        /// The distinct prepend counts per hop (see Route::get_prepend_counts()) of the
+        /// routes, in the order they were first seen
+        pub fn get_prepend_counts(&self) -> Vec<Vec<usize>> {
+            let mut prepend_counts = Vec::<Vec<usize>>::new();
+            for route in &self.routes {
+                let counts = route.get_prepend_counts();
+                if !prepend_counts.contains(&counts) {
+                    prepend_counts.push(counts);
                 }
             }
-            false
+            prepend_counts
+        }
+
+        pub fn get_routes(&self) -> &IndexSet<Route> {
+            &self.routes
+        }
+
+        pub fn into_routes(self) -> IndexSet<Route> {
+            self.routes
+        }
+
+        pub fn has_as_trans(&self) -> bool {
+            self.get_as_path().contains(&Asn::TRANSITION)
+        }
+
+        /// At least one route has a prepended hop
+        pub fn has_prepending(&self) -> bool {
+            self.routes.iter().any(|route| route.has_prepending())
         }
 
         pub fn has_route(&self, route: &Route) -> bool {
@@ -93,7 +144,7 @@ pub mod as_path {
         }
 
         pub fn len(&self) -> usize {
-            self.as_path.len()
+            self.get_as_path().len()
         }
     }
 
@@ -172,6 +223,19 @@ pub mod as_path {
             assert!(ap_1.len() == 3);
             assert!(ap_2.len() == 3);
             assert!(!ap_1.has_divergence_with(&ap_2));
+
+            // AS_TRANS is not treated as a shared ASN
+            let ap_1 = AsPath::new(Vec::from([
+                Asn::new_32bit(1),
+                Asn::TRANSITION,
+                Asn::new_32bit(3),
+            ]));
+            let ap_2 = AsPath::new(Vec::from([
+                Asn::TRANSITION,
+                Asn::new_32bit(5),
+                Asn::new_32bit(3),
+            ]));
+            assert!(!ap_1.has_divergence_with(&ap_2));
         }
     }
 }
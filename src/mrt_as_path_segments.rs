@@ -0,0 +1,257 @@
+pub mod as_path_segments {
+    use bgpkit_parser::models::{AsPath as BgpAsPath, AsPathSegment, Asn};
+
+    /// A path segment which doesn't contribute to the AS sequence (an AS set or a
+    /// confederation segment), stored with its position in the AS sequence.
+    /// The position is the number of AS sequence ASNs which precede the segment.
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    pub struct PositionedSegment {
+        pub position: usize,
+        pub asns: Vec<Asn>,
+    }
+
+    /// The type of a confederation segment
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub enum ConfedSegmentType {
+        Sequence,
+        Set,
+    }
+
+    /// A confederation segment, which should never be seen outside of a confederation
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    pub struct ConfedSegment {
+        pub segment_type: ConfedSegmentType,
+        pub segment: PositionedSegment,
+    }
+
+    /// The AS_PATH and AS4_PATH attributes as received, before they were merged
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    pub struct RawAsPaths {
+        pub as_path: BgpAsPath,
+        pub as4_path: BgpAsPath,
+    }
+
+    /// The segments of an AS path, reconstructed in the order they were received.
+    /// All AS sequence segments are concatenated (paths longer than 255 ASNs and
+    /// prepends spread over several segments are split into multiple segments),
+    /// AS sets keep their position within the sequence, and confederation segments
+    /// are kept separately.
+    #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+    pub struct AsPathSegments {
+        as_sequence: Vec<Asn>,
+        as_sets: Vec<PositionedSegment>,
+        confed_segments: Vec<ConfedSegment>,
+    }
+
+    impl AsPathSegments {
+        pub fn new(as_path: &BgpAsPath) -> Self {
+            let mut segments = AsPathSegments::default();
+
+            for path_seg in &as_path.segments {
+                let position = segments.as_sequence.len();
+                match path_seg {
+                    AsPathSegment::AsSequence(asns) => {
+                        segments.as_sequence.extend_from_slice(asns);
+                    }
+                    AsPathSegment::AsSet(asns) => {
+                        segments.as_sets.push(PositionedSegment {
+                            position,
+                            asns: asns.clone(),
+                        });
+                    }
+                    AsPathSegment::ConfedSequence(asns) => {
+                        segments.confed_segments.push(ConfedSegment {
+                            segment_type: ConfedSegmentType::Sequence,
+                            segment: PositionedSegment {
+                                position,
+                                asns: asns.clone(),
+                            },
+                        });
+                    }
+                    AsPathSegment::ConfedSet(asns) => {
+                        segments.confed_segments.push(ConfedSegment {
+                            segment_type: ConfedSegmentType::Set,
+                            segment: PositionedSegment {
+                                position,
+                                asns: asns.clone(),
+                            },
+                        });
+                    }
+                }
+            }
+
+            segments
+        }
+
+        pub fn get_as_sequence(&self) -> &Vec<Asn> {
+            &self.as_sequence
+        }
+
+        pub fn get_as_sets(&self) -> &Vec<PositionedSegment> {
+            &self.as_sets
+        }
+
+        pub fn get_confed_segments(&self) -> &Vec<ConfedSegment> {
+            &self.confed_segments
+        }
+
+        pub fn has_confed_segments(&self) -> bool {
+            !self.confed_segments.is_empty()
+        }
+
+        /// No AS sequence or AS set ASNs (confederation segments don't count)
+        pub fn is_empty(&self) -> bool {
+            self.as_sequence.is_empty() && self.as_sets.iter().all(|s| s.asns.is_empty())
+        }
+    }
+
+    /// Reconstruct the AS path from AS_PATH and AS4_PATH, as per RFC 6793 section 4.2.3.
+    /// If AS_PATH is shorter than AS4_PATH, AS4_PATH is ignored. Otherwise, as many
+    /// leading ASNs as needed are taken from AS_PATH and prepended to AS4_PATH, so the
+    /// merged path is the same length as AS_PATH. Confederation segments from AS_PATH
+    /// are kept if they are leading or adjacent to a prepended segment, and any in
+    /// AS4_PATH are ignored. AS sets count as one ASN, confederation segments as zero.
+    pub fn merge_as4_path(as_path: &BgpAsPath, as4_path: &BgpAsPath) -> BgpAsPath {
+        let as4_segments: Vec<&AsPathSegment> = as4_path
+            .segments
+            .iter()
+            .filter(|s| !s.is_confed())
+            .collect();
+        let as4_path_len: usize = as4_segments.iter().map(|s| s.route_len()).sum();
+
+        if as_path.route_len() < as4_path_len {
+            return as_path.clone();
+        }
+
+        let mut to_take = as_path.route_len() - as4_path_len;
+        let mut merged = Vec::<AsPathSegment>::new();
+
+        for path_seg in &as_path.segments {
+            if to_take == 0 && !path_seg.is_confed() {
+                break;
+            }
+            match path_seg {
+                AsPathSegment::AsSequence(asns) => {
+                    let n = to_take.min(asns.len());
+                    merged.push(AsPathSegment::AsSequence(asns[..n].to_vec()));
+                    to_take -= n;
+                }
+                AsPathSegment::AsSet(_) => {
+                    merged.push(path_seg.clone());
+                    to_take -= 1;
+                }
+                AsPathSegment::ConfedSequence(_) | AsPathSegment::ConfedSet(_) => {
+                    merged.push(path_seg.clone());
+                }
+            }
+        }
+
+        merged.extend(as4_segments.into_iter().cloned());
+        BgpAsPath::from_segments(merged)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_merge_as4_path() {
+            // AS_TRANS placeholders are replaced by the 4-byte ASNs
+            let as_path = BgpAsPath::from_sequence([1, 2, 23456, 23456]);
+            let as4_path = BgpAsPath::from_sequence([196608, 196609]);
+            let merged = AsPathSegments::new(&merge_as4_path(&as_path, &as4_path));
+            assert_eq!(
+                merged.get_as_sequence(),
+                &Vec::from([1, 2, 196608, 196609].map(Asn::new_32bit))
+            );
+
+            // AS4_PATH longer than AS_PATH is ignored
+            let as_path = BgpAsPath::from_sequence([1, 23456]);
+            let as4_path = BgpAsPath::from_sequence([3, 4, 196608]);
+            let merged = AsPathSegments::new(&merge_as4_path(&as_path, &as4_path));
+            assert_eq!(
+                merged.get_as_sequence(),
+                &Vec::from([1, 23456].map(Asn::new_32bit))
+            );
+
+            // Leading confed segments are kept, AS4_PATH confed segments are dropped
+            let as_path = BgpAsPath::from_segments(Vec::from([
+                AsPathSegment::ConfedSequence(Vec::from([65001].map(Asn::new_32bit))),
+                AsPathSegment::sequence([1, 23456]),
+            ]));
+            let as4_path = BgpAsPath::from_segments(Vec::from([
+                AsPathSegment::ConfedSequence(Vec::from([65002].map(Asn::new_32bit))),
+                AsPathSegment::sequence([196608]),
+            ]));
+            let merged = AsPathSegments::new(&merge_as4_path(&as_path, &as4_path));
+            assert_eq!(merged.get_confed_segments().len(), 1);
+            assert_eq!(
+                merged.get_confed_segments()[0].segment.asns,
+                Vec::from([Asn::new_32bit(65001)])
+            );
+            assert_eq!(
+                merged.get_as_sequence(),
+                &Vec::from([1, 196608].map(Asn::new_32bit))
+            );
+        }
+
+        #[test]
+        fn test_multiple_sequences_are_concatenated() {
+            let as_path = BgpAsPath::from_segments(Vec::from([
+                AsPathSegment::sequence([1, 2, 2]),
+                AsPathSegment::sequence([2, 3]),
+            ]));
+            let segments = AsPathSegments::new(&as_path);
+            assert_eq!(
+                segments.get_as_sequence(),
+                &Vec::from([1, 2, 2, 2, 3].map(Asn::new_32bit))
+            );
+            assert!(segments.get_as_sets().is_empty());
+        }
+
+        #[test]
+        fn test_as_set_keeps_position() {
+            let as_path = BgpAsPath::from_segments(Vec::from([
+                AsPathSegment::sequence([1, 2]),
+                AsPathSegment::set([3, 4]),
+                AsPathSegment::sequence([5]),
+            ]));
+            let segments = AsPathSegments::new(&as_path);
+            assert_eq!(
+                segments.get_as_sequence(),
+                &Vec::from([1, 2, 5].map(Asn::new_32bit))
+            );
+            assert_eq!(segments.get_as_sets()[0].position, 2);
+            assert_eq!(
+                segments.get_as_sets()[0].asns,
+                Vec::from([3, 4].map(Asn::new_32bit))
+            );
+        }
+
+        #[test]
+        fn test_confed_segments_are_recorded() {
+            let as_path = BgpAsPath::from_segments(Vec::from([
+                AsPathSegment::ConfedSequence(Vec::from([65001, 65002].map(Asn::new_32bit))),
+                AsPathSegment::sequence([1, 2]),
+            ]));
+            let segments = AsPathSegments::new(&as_path);
+            assert!(segments.has_confed_segments());
+            assert_eq!(
+                segments.get_confed_segments()[0].segment_type,
+                ConfedSegmentType::Sequence
+            );
+            assert_eq!(segments.get_confed_segments()[0].segment.position, 0);
+            assert_eq!(
+                segments.get_as_sequence(),
+                &Vec::from([1, 2].map(Asn::new_32bit))
+            );
+
+            // Confed only paths are internal to the confederation
+            let as_path = BgpAsPath::from_segments(Vec::from([AsPathSegment::ConfedSequence(
+                Vec::from([65001].map(Asn::new_32bit)),
+            )]));
+            let segments = AsPathSegments::new(&as_path);
+            assert!(segments.is_empty());
+        }
+    }
+}
@@ -1,6 +1,35 @@
 pub mod asn {
     use bgpkit_parser::models::Asn;
 
+    /// The IANA special purpose ASN ranges (see Asn::is_reserved())
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub enum SpecialPurposeType {
+        /// 64512-65534 and 4200000000-4294967294
+        Private,
+        /// 64496-64511 and 65536-65551
+        Documentation,
+        /// Everything else in the registry, e.g. 0, AS_TRANS and 65535
+        Reserved,
+    }
+
+    pub fn get_special_purpose_type(asn: &Asn) -> Option<SpecialPurposeType> {
+        if asn.is_private() {
+            Some(SpecialPurposeType::Private)
+        } else if asn.is_reserved_for_documentation() {
+            Some(SpecialPurposeType::Documentation)
+        } else if asn.is_reserved() {
+            Some(SpecialPurposeType::Reserved)
+        } else {
+            None
+        }
+    }
+
+    /// Private, reserved (including AS_TRANS and AS 0) and documentation ASNs,
+    /// which don't identify a real network
+    pub fn is_special_purpose(asn: &Asn) -> bool {
+        get_special_purpose_type(asn).is_some()
+    }
+
     pub trait Testing {
         fn get_mock(asn: Option<u32>) -> Asn;
     }
@@ -28,3 +28,87 @@ pub mod communities {
         ])
     }
 }
+
+pub mod shared_communities {
+    use crate::mrt_interner::interner::Interner;
+    use bgpkit_parser::models::Community;
+    use std::fmt;
+    use std::hash::{Hash, Hasher};
+    use std::sync::LazyLock;
+
+    /// Every distinct list of communities seen so far
+    static COMMUNITIES: LazyLock<Interner<CommunityList>> = LazyLock::new(Interner::new);
+
+    /// Community isn't Hash, so encode each one as an integer. Well-known
+    /// communities are tagged above the 48 bits used by ASN:value.
+    fn get_community_key(community: &Community) -> u64 {
+        match community {
+            Community::NoExport => 1 << 48 | 0xFFFFFF01,
+            Community::NoAdvertise => 1 << 48 | 0xFFFFFF02,
+            Community::NoExportSubConfed => 1 << 48 | 0xFFFFFF03,
+            Community::Custom(asn, value) => (asn.to_u32() as u64) << 16 | *value as u64,
+        }
+    }
+
+    /// A list of communities which can be interned, hashed by get_community_key()
+    #[derive(Eq, PartialEq)]
+    struct CommunityList(Vec<Community>);
+
+    impl Hash for CommunityList {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write_usize(self.0.len());
+            for community in &self.0 {
+                get_community_key(community).hash(state);
+            }
+        }
+    }
+
+    /// The communities attached to a route. Most routes carry one of a small number of
+    /// community lists, so each distinct list is interned and shared between routes.
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct SharedCommunities(u32);
+
+    impl SharedCommunities {
+        pub fn new(communities: Vec<Community>) -> Self {
+            SharedCommunities(COMMUNITIES.intern(CommunityList(communities)))
+        }
+
+        pub fn get_communities(&self) -> &'static [Community] {
+            &COMMUNITIES.get(self.0).0
+        }
+    }
+
+    /// Debug output shows the communities rather than the ID
+    impl fmt::Debug for SharedCommunities {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get_communities().fmt(f)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mrt_community::communities::get_mock;
+        use bgpkit_parser::models::Asn;
+
+        #[test]
+        fn test_communities_are_interned() {
+            let a = SharedCommunities::new(get_mock(None));
+            let b = SharedCommunities::new(get_mock(None));
+            assert_eq!(a, b);
+            assert!(std::ptr::eq(a.get_communities(), b.get_communities()));
+
+            let c = SharedCommunities::new(get_mock(Some((Asn::new_32bit(3), 3))));
+            assert_ne!(a, c);
+            assert_eq!(c.get_communities().len(), 3);
+
+            // Well-known communities don't collide with ASN:value communities
+            let no_export = SharedCommunities::new(Vec::from([Community::NoExport]));
+            let custom = SharedCommunities::new(Vec::from([Community::Custom(
+                Asn::new_16bit(65535),
+                0xFF01,
+            )]));
+            assert_ne!(no_export, custom);
+        }
+    }
+}
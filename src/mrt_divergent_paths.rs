@@ -0,0 +1,64 @@
+pub mod divergent_paths {
+    use crate::mrt_as_path::as_path::AsPath;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::{Asn, Peer};
+
+    /// Two AS paths to the same origin which share an ASN, but take a
+    /// different number of hops from that ASN to the origin.
+    #[derive(Debug)]
+    pub struct DivergentPaths<'a> {
+        pub origin: Asn,
+        pub shared_asn: Asn,
+        pub as_path_a: &'a AsPath,
+        pub as_path_b: &'a AsPath,
+        /// From the shared ASN to the origin, inclusive
+        pub sub_path_a: Vec<Asn>,
+        pub sub_path_b: Vec<Asn>,
+        /// The distinct prepend counts per hop of each AS path, across its routes
+        pub prepend_counts_a: Vec<Vec<usize>>,
+        pub prepend_counts_b: Vec<Vec<usize>>,
+        /// Routes for both AS paths
+        pub routes: Vec<&'a Route>,
+        /// Unique peers and files of the routes
+        pub peers: Vec<&'a Peer>,
+        pub filenames: Vec<&'a String>,
+    }
+
+    impl<'a> DivergentPaths<'a> {
+        /// Return the divergence between two AS paths, if there is one
+        pub fn new(origin: Asn, as_path_a: &'a AsPath, as_path_b: &'a AsPath) -> Option<Self> {
+            let (shared_asn, sub_path_a, sub_path_b) = as_path_a.get_divergence_with(as_path_b)?;
+
+            let routes: Vec<&Route> = as_path_a
+                .get_routes()
+                .iter()
+                .chain(as_path_b.get_routes())
+                .collect();
+
+            let mut peers = Vec::<&Peer>::new();
+            let mut filenames = Vec::<&String>::new();
+            for route in &routes {
+                if !peers.contains(&route.get_peer()) {
+                    peers.push(route.get_peer());
+                }
+                if !filenames.contains(&route.get_filename()) {
+                    filenames.push(route.get_filename());
+                }
+            }
+
+            Some(DivergentPaths {
+                origin,
+                shared_asn,
+                as_path_a,
+                as_path_b,
+                sub_path_a: sub_path_a.to_vec(),
+                sub_path_b: sub_path_b.to_vec(),
+                prepend_counts_a: as_path_a.get_prepend_counts(),
+                prepend_counts_b: as_path_b.get_prepend_counts(),
+                routes,
+                peers,
+                filenames,
+            })
+        }
+    }
+}
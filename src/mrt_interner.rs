@@ -0,0 +1,310 @@
+pub mod interner {
+    use crate::mrt_as_path_segments::as_path_segments::RawAsPaths;
+    use bgpkit_parser::models::{Asn, Peer};
+    use std::borrow::Borrow;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::hash::{Hash, Hasher};
+    use std::sync::{LazyLock, OnceLock, RwLock};
+
+    /// Slot i of bucket b holds ID 2^b - 1 + i, so bucket b has 2^b slots and
+    /// the 32 buckets cover every u32 ID
+    const BUCKETS: usize = 32;
+
+    /// An append-only table of unique values, each identified by a u32 ID.
+    /// Interned values live for the rest of the program (they are leaked), so
+    /// they can be handed out as &'static references. The values are stored in
+    /// buckets which are never moved or resized once allocated, so looking up an
+    /// ID doesn't take a lock, only interning a value does.
+    pub struct Interner<T: 'static> {
+        ids: RwLock<HashMap<Interned<T>, u32>>,
+        buckets: [OnceLock<Box<[OnceLock<&'static T>]>>; BUCKETS],
+    }
+
+    /// An interned value as a key of the ID table, it hashes and compares as the value
+    /// so the table can be searched with anything the value can be borrowed as
+    pub struct Interned<T: 'static>(&'static T);
+
+    impl<T: Hash> Hash for Interned<T> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    impl<T: PartialEq> PartialEq for Interned<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl<T: Eq> Eq for Interned<T> {}
+
+    impl<T> Borrow<T> for Interned<T> {
+        fn borrow(&self) -> &T {
+            self.0
+        }
+    }
+
+    impl Borrow<str> for Interned<String> {
+        fn borrow(&self) -> &str {
+            self.0
+        }
+    }
+
+    /// The bucket and the slot within it for an ID
+    fn get_slot(id: u32) -> (usize, usize) {
+        let position = id as u64 + 1;
+        let bucket = (u64::BITS - 1 - position.leading_zeros()) as usize;
+        (bucket, (position - (1 << bucket)) as usize)
+    }
+
+    impl<T: Eq + Hash + Sync + 'static> Default for Interner<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Eq + Hash + Sync + 'static> Interner<T> {
+        pub fn new() -> Self {
+            Interner {
+                ids: RwLock::new(HashMap::new()),
+                buckets: [const { OnceLock::new() }; BUCKETS],
+            }
+        }
+
+        /// Return the ID of value, adding it to the table if it's new
+        pub fn intern(&self, value: T) -> u32 {
+            if let Some(id) = self.ids.read().unwrap().get(&value) {
+                return *id;
+            }
+            self.insert(value)
+        }
+
+        /// Return the ID of a borrowed value (e.g. a &str for a String), the owned
+        /// value is only allocated if it isn't in the table yet
+        pub fn intern_ref<Q>(&self, value: &Q) -> u32
+        where
+            Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+            Interned<T>: Borrow<Q>,
+        {
+            if let Some(id) = self.ids.read().unwrap().get(value) {
+                return *id;
+            }
+            self.insert(value.to_owned())
+        }
+
+        fn insert(&self, value: T) -> u32 {
+            let mut ids = self.ids.write().unwrap();
+            // Another thread may have added it between the locks
+            if let Some(id) = ids.get(&value) {
+                return *id;
+            }
+            let id = u32::try_from(ids.len())
+                .ok()
+                .filter(|id| *id != u32::MAX)
+                .unwrap_or_else(|| panic!("Interning table is full"));
+            let value: &'static T = Box::leak(Box::new(value));
+
+            // The slot is filled before the ID is handed out, so get() always finds it
+            let (bucket, slot) = get_slot(id);
+            self.buckets[bucket]
+                .get_or_init(|| (0..1usize << bucket).map(|_| OnceLock::new()).collect())[slot]
+                .set(value)
+                .unwrap_or_else(|_| panic!("Interned ID {} already in use", id));
+            ids.insert(Interned(value), id);
+            id
+        }
+
+        pub fn get(&self, id: u32) -> &'static T {
+            let (bucket, slot) = get_slot(id);
+            self.buckets[bucket]
+                .get()
+                .and_then(|slots| slots[slot].get())
+                .unwrap_or_else(|| panic!("Interned ID {} doesn't exist", id))
+        }
+
+        pub fn len(&self) -> usize {
+            self.ids.read().unwrap().len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+    }
+
+    pub static AS_PATHS: LazyLock<Interner<Vec<Asn>>> = LazyLock::new(Interner::new);
+    pub static COLLECTORS: LazyLock<Interner<String>> = LazyLock::new(Interner::new);
+    pub static FILENAMES: LazyLock<Interner<String>> = LazyLock::new(Interner::new);
+    pub static PEERS: LazyLock<Interner<Peer>> = LazyLock::new(Interner::new);
+    pub static RAW_AS_PATHS: LazyLock<Interner<RawAsPaths>> = LazyLock::new(Interner::new);
+    pub static SNAPSHOTS: LazyLock<Interner<String>> = LazyLock::new(Interner::new);
+
+    /// An interned AS path (a sequence of ASNs)
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct AsPathId(u32);
+
+    impl AsPathId {
+        pub fn new(as_path: Vec<Asn>) -> Self {
+            AsPathId(AS_PATHS.intern(as_path))
+        }
+
+        pub fn get(&self) -> &'static Vec<Asn> {
+            AS_PATHS.get(self.0)
+        }
+    }
+
+    /// An interned MRT filename
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct FilenameId(u32);
+
+    impl FilenameId {
+        pub fn new(filename: &str) -> Self {
+            FilenameId(FILENAMES.intern_ref(filename))
+        }
+
+        pub fn get(&self) -> &'static String {
+            FILENAMES.get(self.0)
+        }
+    }
+
+    /// An interned route collector ID, e.g. rrc00 or route-views2
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct CollectorId(u32);
+
+    impl CollectorId {
+        pub fn new(collector: &str) -> Self {
+            CollectorId(COLLECTORS.intern_ref(collector))
+        }
+
+        pub fn get(&self) -> &'static String {
+            COLLECTORS.get(self.0)
+        }
+    }
+
+    /// An interned RIB snapshot, the start time of the dump, e.g. 2025-09-22 08:00
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct SnapshotId(u32);
+
+    impl SnapshotId {
+        pub fn new(snapshot: &str) -> Self {
+            SnapshotId(SNAPSHOTS.intern_ref(snapshot))
+        }
+
+        pub fn get(&self) -> &'static String {
+            SNAPSHOTS.get(self.0)
+        }
+    }
+
+    /// An interned BGP peer
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct PeerId(u32);
+
+    impl PeerId {
+        pub fn new(peer: Peer) -> Self {
+            PeerId(PEERS.intern(peer))
+        }
+
+        pub fn get(&self) -> &'static Peer {
+            PEERS.get(self.0)
+        }
+    }
+
+    /// Interned AS_PATH and AS4_PATH attributes, most routes with an AS4_PATH share
+    /// them with other routes from the same peer
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct RawAsPathsId(u32);
+
+    impl RawAsPathsId {
+        pub fn new(raw_as_paths: RawAsPaths) -> Self {
+            RawAsPathsId(RAW_AS_PATHS.intern(raw_as_paths))
+        }
+
+        pub fn get(&self) -> &'static RawAsPaths {
+            RAW_AS_PATHS.get(self.0)
+        }
+    }
+
+    /// Debug output shows the interned value rather than the ID
+    impl fmt::Debug for AsPathId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    impl fmt::Debug for CollectorId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    impl fmt::Debug for FilenameId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    impl fmt::Debug for PeerId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    impl fmt::Debug for RawAsPathsId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    impl fmt::Debug for SnapshotId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get().fmt(f)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_interned_ids() {
+            let a = AsPathId::new(Vec::from([1, 2, 3].map(Asn::new_32bit)));
+            let b = AsPathId::new(Vec::from([1, 2, 3].map(Asn::new_32bit)));
+            let c = AsPathId::new(Vec::from([1, 2].map(Asn::new_32bit)));
+            assert_eq!(a, b);
+            assert_ne!(a, c);
+            assert_eq!(a.get(), &Vec::from([1, 2, 3].map(Asn::new_32bit)));
+
+            let f = FilenameId::new("rib.20250922.0000.bz2");
+            assert_eq!(f, FilenameId::new("rib.20250922.0000.bz2"));
+            assert_eq!(f.get(), "rib.20250922.0000.bz2");
+        }
+
+        #[test]
+        fn test_intern_ref() {
+            let interner = Interner::<String>::new();
+            let id = interner.intern_ref("rrc00");
+            assert_eq!(interner.intern_ref("rrc00"), id);
+            assert_eq!(interner.intern(String::from("rrc00")), id);
+            assert_ne!(interner.intern_ref("rrc01"), id);
+            assert_eq!(interner.len(), 2);
+        }
+
+        #[test]
+        fn test_get_slot() {
+            assert_eq!(get_slot(0), (0, 0));
+            assert_eq!(get_slot(1), (1, 0));
+            assert_eq!(get_slot(2), (1, 1));
+            assert_eq!(get_slot(3), (2, 0));
+            assert_eq!(get_slot(u32::MAX - 1), (31, (1 << 31) - 1));
+
+            // IDs across several buckets are all found again
+            let interner = Interner::<u32>::new();
+            for value in 0..100 {
+                assert_eq!(interner.intern(value), value);
+            }
+            assert_eq!(interner.intern(42), 42);
+            assert!((0..100).all(|id| *interner.get(id) == id));
+            assert_eq!(interner.len(), 100);
+        }
+    }
+}
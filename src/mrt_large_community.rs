@@ -26,3 +26,48 @@ pub mod large_communities {
         ])
     }
 }
+
+pub mod shared_large_communities {
+    use crate::mrt_interner::interner::Interner;
+    use bgpkit_parser::models::LargeCommunity;
+    use std::fmt;
+    use std::hash::{Hash, Hasher};
+    use std::sync::LazyLock;
+
+    /// Every distinct list of large communities seen so far
+    static LARGE_COMMUNITIES: LazyLock<Interner<LargeCommunityList>> = LazyLock::new(Interner::new);
+
+    /// A list of large communities which can be interned, LargeCommunity isn't Hash
+    #[derive(Eq, PartialEq)]
+    struct LargeCommunityList(Vec<LargeCommunity>);
+
+    impl Hash for LargeCommunityList {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write_usize(self.0.len());
+            for c in &self.0 {
+                [c.global_admin, c.local_data[0], c.local_data[1]].hash(state);
+            }
+        }
+    }
+
+    /// The large communities attached to a route, interned and shared between routes
+    #[derive(Clone, Copy, Eq, Hash, PartialEq)]
+    pub struct SharedLargeCommunities(u32);
+
+    impl SharedLargeCommunities {
+        pub fn new(large_communities: Vec<LargeCommunity>) -> Self {
+            SharedLargeCommunities(LARGE_COMMUNITIES.intern(LargeCommunityList(large_communities)))
+        }
+
+        pub fn get_large_communities(&self) -> &'static [LargeCommunity] {
+            &LARGE_COMMUNITIES.get(self.0).0
+        }
+    }
+
+    /// Debug output shows the large communities rather than the ID
+    impl fmt::Debug for SharedLargeCommunities {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.get_large_communities().fmt(f)
+        }
+    }
+}
@@ -1,16 +1,21 @@
 pub mod origin_as_paths {
     use crate::mrt_as_path::as_path::AsPath;
     use crate::mrt_asn::asn::Testing;
+    use crate::mrt_divergent_paths::divergent_paths::DivergentPaths;
+    use crate::mrt_interner::interner::AsPathId;
     use crate::mrt_route::route::Route;
     use bgpkit_parser::models::Asn;
+    use indexmap::IndexMap;
+    use indexmap::map::Values;
     use log::debug;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-    /// A vector of unique, deduped AS paths, which all point to the same origin ASN
+    /// Unique, deduped AS paths, which all point to the same origin ASN.
+    /// Keyed by the deduped ASN sequence, in the order they were added.
     #[derive(Debug, Clone)]
     pub struct OriginAsPaths {
         origin: Asn,
-        as_paths: Vec<AsPath>,
+        as_paths: IndexMap<AsPathId, AsPath>,
     }
 
     impl PartialEq for OriginAsPaths {
@@ -23,94 +28,138 @@ pub mod origin_as_paths {
         pub fn new(origin: Asn) -> Self {
             OriginAsPaths {
                 origin,
-                as_paths: Vec::<AsPath>::new(),
+                as_paths: IndexMap::<AsPathId, AsPath>::new(),
             }
         }
 
         pub fn get_mock(origin: Option<Asn>) -> OriginAsPaths {
             let o = origin.unwrap_or(Asn::get_mock(None));
-            OriginAsPaths {
-                origin: o,
-                as_paths: Vec::from([AsPath::get_mock(Some(o))]),
-            }
+            let mut origin_as_paths = OriginAsPaths::new(o);
+            origin_as_paths.add_as_path(AsPath::get_mock(Some(o)));
+            origin_as_paths
         }
 
+        /// Add an AS path, if the ASN sequence already exists its routes are added
+        /// to the existing AS path, so there is only ever one AsPath per ASN sequence
+        /// with the union of the routes
         pub fn add_as_path(&mut self, as_path: AsPath) {
-            if self.has_as_path(&as_path) {
+            if let Some(existing) = self.as_paths.get_mut(&as_path.get_as_path_id()) {
+                for route in as_path.into_routes() {
+                    existing.add_route(route);
+                }
                 return;
             };
-            self.as_paths.push(as_path);
+            self.as_paths.insert(as_path.get_as_path_id(), as_path);
         }
 
         pub fn add_route(&mut self, route: Route) {
-            let as_path = AsPath::new(route.get_as_path().clone());
-            self.get_as_path_mut(&as_path).add_route(route);
+            let key = route.get_deduped_as_path_id();
+            self.get_as_path_mut(key).add_route(route);
         }
 
-        pub fn find_divergent_paths(&self) -> HashMap<AsPath, Vec<&AsPath>> {
-            let mut divergent_paths: HashMap<AsPath, Vec<&AsPath>> = HashMap::new();
-
-            let mut checked = Vec::<&AsPath>::new();
+        /// Index every transit ASN (not the origin, and not AS_TRANS) by its distance from
+        /// the origin in each AS path. This is the depth of the ASN in a tree of the reversed
+        /// AS paths, so two paths diverge if they share an ASN at different depths.
+        /// Only the first occurrence of an ASN in a path is indexed, as per
+        /// AsPath::has_divergence_with().
+        fn get_depth_index(&self) -> HashMap<Asn, BTreeMap<usize, Vec<usize>>> {
+            let mut index = HashMap::<Asn, BTreeMap<usize, Vec<usize>>>::new();
 
-            for a in self.get_as_paths() {
-                for b in self.get_as_paths() {
-                    if a == b {
-                        continue;
-                    };
-                    if checked.contains(&a) {
+            for (path_id, as_path) in self.get_as_paths().enumerate() {
+                let Some((_, transit)) = as_path.get_as_path().split_last() else {
+                    continue;
+                };
+                let mut seen = HashSet::<Asn>::with_capacity(transit.len());
+                for (pos, asn) in transit.iter().enumerate() {
+                    if *asn == Asn::TRANSITION || !seen.insert(*asn) {
                         continue;
                     }
-                    if a.has_divergence_with(b) {
-                        if !divergent_paths.contains_key(a) {
-                            divergent_paths.insert(a.clone(), Vec::new());
-                        };
-                        divergent_paths.get_mut(a).unwrap().push(b);
+                    let depth = transit.len() - pos;
+                    index
+                        .entry(*asn)
+                        .or_default()
+                        .entry(depth)
+                        .or_default()
+                        .push(path_id);
+                }
+            }
+            index
+        }
+
+        /// Return every pair of AS paths which share a transit ASN at a different depth.
+        /// Pairs are only generated from paths indexed under the same ASN, so the cost is
+        /// linear in the total path length plus the number of divergent pairs, rather than
+        /// comparing every pair of paths.
+        fn get_divergent_pairs(&self) -> BTreeSet<(usize, usize)> {
+            let mut pairs = BTreeSet::<(usize, usize)>::new();
+
+            for depths in self.get_depth_index().values() {
+                if depths.len() < 2 {
+                    continue;
+                }
+                let buckets: Vec<&Vec<usize>> = depths.values().collect();
+                for (i, bucket_a) in buckets.iter().enumerate() {
+                    for bucket_b in &buckets[i + 1..] {
+                        for a in bucket_a.iter() {
+                            for b in bucket_b.iter() {
+                                pairs.insert((*a.min(b), *a.max(b)));
+                            }
+                        }
                     }
                 }
-                checked.push(a);
             }
-            divergent_paths
+            pairs
         }
 
-        fn get_as_paths(&self) -> &Vec<AsPath> {
-            &self.as_paths
+        /// Return each pair of AS paths which diverge after a shared ASN, once.
+        /// Single-hop paths have no transit ASNs, so they are never indexed or paired.
+        pub fn find_divergent_paths(&self) -> Vec<DivergentPaths<'_>> {
+            self.get_divergent_pairs()
+                .into_iter()
+                .filter_map(|(a, b)| {
+                    DivergentPaths::new(self.origin, &self.as_paths[a], &self.as_paths[b])
+                })
+                .collect()
         }
 
-        fn get_as_paths_mut(&mut self) -> &mut Vec<AsPath> {
-            self.as_paths.as_mut()
+        /// Return the AS paths which still contain AS_TRANS after the AS4_PATH merge
+        pub fn find_as_trans_paths(&self) -> Vec<&AsPath> {
+            self.get_as_paths()
+                .filter(|as_path| as_path.has_as_trans())
+                .collect()
         }
 
-        fn get_as_path(&self, as_path: &AsPath) -> &AsPath {
-            for a in self.get_as_paths() {
-                if a.get_as_path() == as_path.get_as_path() {
-                    return a;
-                }
-            }
-            panic!("AS Path not found {:#?}", as_path);
+        pub fn get_as_paths(&self) -> Values<'_, AsPathId, AsPath> {
+            self.as_paths.values()
         }
 
-        fn get_as_path_mut(&mut self, as_path: &AsPath) -> &mut AsPath {
-            for a in self.get_as_paths_mut() {
-                if a.get_as_path() == as_path.get_as_path() {
-                    return a;
-                }
-            }
-            panic!("AS Path not found {:#?}", as_path);
+        fn get_as_path(&self, key: AsPathId) -> &AsPath {
+            self.as_paths
+                .get(&key)
+                .unwrap_or_else(|| panic!("AS Path not found {:?}", key))
+        }
+
+        fn get_as_path_mut(&mut self, key: AsPathId) -> &mut AsPath {
+            self.as_paths
+                .get_mut(&key)
+                .unwrap_or_else(|| panic!("AS Path not found {:?}", key))
+        }
+
+        pub fn get_routes_count(&self) -> usize {
+            self.get_as_paths()
+                .map(|as_path| as_path.get_routes().len())
+                .sum()
         }
 
         pub fn get_origin(&self) -> &Asn {
             &self.origin
         }
 
+        /// An AS path with the same ASN sequence exists (routes aren't compared)
         pub fn has_as_path(&self, as_path: &AsPath) -> bool {
-            for a in self.get_as_paths() {
-                if a == as_path {
-                    debug!("AS path found: {:#?}", as_path);
-                    return true;
-                }
-            }
-            debug!("AS path not found: {:#?}", as_path);
-            false
+            let present = self.as_paths.contains_key(&as_path.get_as_path_id());
+            debug!("AS path present {:?}: {}", as_path.get_as_path(), present);
+            present
         }
 
         pub fn has_route(&self, route: &Route) -> bool {
@@ -121,12 +170,11 @@ pub mod origin_as_paths {
                     route
                 )
             };
-            let as_path = AsPath::new(route.get_as_path().clone());
-            if !self.has_as_path(&as_path) {
+            let key = route.get_deduped_as_path_id();
+            if !self.as_paths.contains_key(&key) {
                 return false;
             };
-            let as_path = self.get_as_path(&as_path);
-            as_path.has_route(route)
+            self.get_as_path(key).has_route(route)
         }
 
         pub fn is_empty(&self) -> bool {
@@ -137,28 +185,20 @@ pub mod origin_as_paths {
             self.as_paths.len()
         }
 
-        pub fn merge_from(&mut self, other: &Self) {
-            for as_path in other.get_as_paths() {
-                self.add_as_path(as_path.clone());
+        /// Move all AS paths and their routes from other to self
+        pub fn move_from(&mut self, other: OriginAsPaths) {
+            for as_path in other.as_paths.into_values() {
+                self.add_as_path(as_path);
             }
         }
 
-        fn remove_as_path(&mut self, as_path: &AsPath) {
-            self.as_paths
-                .remove(self.as_paths.iter().position(|x| x == as_path).unwrap());
-        }
-
         pub fn remove_single_hop_paths(&mut self) {
-            let mut to_remove = Vec::new();
-            for as_path in self.get_as_paths() {
-                if as_path.len() == 1 {
-                    to_remove.push(as_path.clone());
-                }
-            }
-            debug!("Single-hop AS Paths to remove: {}", to_remove.len());
-            for as_path in to_remove {
-                self.remove_as_path(&as_path);
-            }
+            let before = self.as_paths.len();
+            self.as_paths.retain(|_, as_path| as_path.len() != 1);
+            debug!(
+                "Single-hop AS Paths removed: {}",
+                before - self.as_paths.len()
+            );
         }
     }
 
@@ -177,6 +217,99 @@ pub mod origin_as_paths {
             assert_eq!(oap_1, oap_2);
         }
 
+        #[test]
+        fn test_find_divergent_paths() {
+            let mut oap = OriginAsPaths::new(Asn::new_32bit(3));
+            oap.add_as_path(AsPath::new(Vec::from([1, 2, 3].map(Asn::new_32bit))));
+            oap.add_as_path(AsPath::new(Vec::from([4, 2, 5, 3].map(Asn::new_32bit))));
+            oap.add_as_path(AsPath::new(Vec::from([6, 7, 3].map(Asn::new_32bit))));
+
+            // Each pair is only reported once
+            let divergent_paths = oap.find_divergent_paths();
+            assert_eq!(divergent_paths.len(), 1);
+            assert_eq!(divergent_paths[0].origin, Asn::new_32bit(3));
+            assert_eq!(divergent_paths[0].shared_asn, Asn::new_32bit(2));
+            assert_eq!(
+                divergent_paths[0].sub_path_a,
+                Vec::from([2, 3].map(Asn::new_32bit))
+            );
+            assert_eq!(
+                divergent_paths[0].sub_path_b,
+                Vec::from([2, 5, 3].map(Asn::new_32bit))
+            );
+        }
+
+        #[test]
+        fn test_find_divergent_paths_matches_pairwise() {
+            let origin = Asn::new_32bit(9);
+            let mut oap = OriginAsPaths::new(origin);
+            let paths: [&[u32]; 8] = [
+                &[1, 2, 9],
+                &[1, 3, 2, 9],
+                &[4, 2, 5, 6, 9],
+                &[7, 23456, 9],
+                &[8, 23456, 5, 9],
+                &[2, 1, 2, 9],
+                &[5, 6, 9],
+                &[9],
+            ];
+            for path in paths {
+                oap.add_as_path(AsPath::new(
+                    path.iter().map(|a| Asn::new_32bit(*a)).collect(),
+                ));
+            }
+
+            let mut pairwise = Vec::new();
+            let as_paths: Vec<&AsPath> = oap.get_as_paths().collect();
+            for (i, a) in as_paths.iter().enumerate() {
+                for b in &as_paths[i + 1..] {
+                    if a.has_divergence_with(b) {
+                        let (asn, _, _) = a.get_divergence_with(b).unwrap();
+                        pairwise.push((a.get_as_path(), b.get_as_path(), asn));
+                    }
+                }
+            }
+
+            let indexed: Vec<(&Vec<Asn>, &Vec<Asn>, Asn)> = oap
+                .find_divergent_paths()
+                .iter()
+                .map(|d| {
+                    (
+                        d.as_path_a.get_as_path(),
+                        d.as_path_b.get_as_path(),
+                        d.shared_asn,
+                    )
+                })
+                .collect();
+            assert!(!indexed.is_empty());
+            assert_eq!(indexed, pairwise);
+        }
+
+        #[test]
+        fn test_prepended_routes_share_as_path() {
+            let origin = Asn::new_32bit(3);
+            let mut oap = OriginAsPaths::new(origin);
+            for as_path in [[1, 2, 3].as_slice(), &[1, 2, 2, 3, 3], &[1, 1, 2, 3]] {
+                let route = Route::mock(Some(origin)).with_as_path(as_path).build();
+                oap.add_as_path(AsPath::new(route.get_deduped_as_path().clone()));
+                oap.add_route(route);
+            }
+
+            // Grouped by the deduped path, with the raw paths kept on the routes
+            assert_eq!(oap.len(), 1);
+            let as_path = oap.get_as_paths().next().unwrap();
+            assert_eq!(
+                as_path.get_as_path(),
+                &Vec::from([1, 2, 3].map(Asn::new_32bit))
+            );
+            assert_eq!(as_path.get_routes().len(), 3);
+            assert!(as_path.has_prepending());
+            assert_eq!(
+                as_path.get_prepend_counts(),
+                Vec::from([vec![1, 1, 1], vec![1, 2, 2], vec![2, 1, 1]])
+            );
+        }
+
         #[test]
         fn test_origin_as_paths_ne() {
             let oap_1 = OriginAsPaths::get_mock(Some(Asn::new_32bit(1)));
@@ -1,18 +1,24 @@
 pub mod path_data {
     use crate::mrt_as_path::as_path::AsPath;
+    use crate::mrt_divergent_paths::divergent_paths::DivergentPaths;
     use crate::mrt_origin_as_paths::origin_as_paths::OriginAsPaths;
     use crate::mrt_route::route::Route;
     use bgpkit_parser::models::Asn;
     use core::panic;
+    use indexmap::IndexSet;
     use log::{debug, info};
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
     use std::collections::HashMap;
-    use std::collections::hash_map::{Keys, Values, ValuesMut};
+    use std::collections::hash_map::{Entry, Values, ValuesMut};
 
     /// Public API which provides access to all paths and routes.
     /// Store all OriginAsPaths keyed by origin ASN.
     #[derive(Debug)]
     pub struct PathData {
         as_paths: HashMap<Asn, OriginAsPaths>,
+        /// Routes with an AS set, which hides the true origin(s), so they aren't
+        /// grouped by origin with the other routes
+        as_set_routes: IndexSet<Route>,
     }
 
     impl Default for PathData {
@@ -23,7 +29,7 @@ pub mod path_data {
 
     impl PartialEq for PathData {
         fn eq(&self, other: &Self) -> bool {
-            self.as_paths == other.as_paths
+            (self.as_paths == other.as_paths) && (self.as_set_routes == other.as_set_routes)
         }
     }
 
@@ -31,6 +37,7 @@ pub mod path_data {
         pub fn new() -> Self {
             PathData {
                 as_paths: HashMap::<Asn, OriginAsPaths>::new(),
+                as_set_routes: IndexSet::<Route>::new(),
             }
         }
 
@@ -47,31 +54,44 @@ pub mod path_data {
             self.as_paths.insert(origin, OriginAsPaths::new(origin));
         }
 
-        fn add_origin_as_paths(&mut self, origin: &Asn, origin_as_paths: &OriginAsPaths) {
-            if !self.has_as_paths_for_origin(origin) {
-                self.add_origin(*origin);
-            }
-            self.get_as_paths_for_origin_mut(origin)
-                .merge_from(origin_as_paths);
-        }
-
         fn add_route(&mut self, route: Route) {
             self.get_as_paths_for_origin_mut(route.get_origin())
                 .add_route(route);
         }
 
-        pub fn find_origins_with_divergent_paths(&self) {
+        /// Return all AS paths where AS_TRANS hides a 4-byte ASN, this is a
+        /// separate class of hidden hop to divergent paths.
+        pub fn find_as_trans_paths(&self) -> Vec<&AsPath> {
+            info!("Searching for AS paths containing AS_TRANS");
+            let as_trans_paths: Vec<&AsPath> = self
+                .get_as_paths()
+                .flat_map(|origin_as_paths| origin_as_paths.find_as_trans_paths())
+                .collect();
+            info!(
+                "Found {} AS paths containing AS_TRANS",
+                as_trans_paths.len()
+            );
+            as_trans_paths
+        }
+
+        /// Search every origin, in parallel, for pairs of AS paths which diverge
+        /// after a shared ASN. Findings are sorted by origin then shared ASN.
+        /// Origins with a single AS path can't diverge so they are skipped, as are
+        /// single-hop paths (see OriginAsPaths::find_divergent_paths()).
+        pub fn find_origins_with_divergent_paths(&self) -> Vec<DivergentPaths<'_>> {
             info!("Searching for divergent paths");
-            for origin_as_paths in self.get_as_paths() {
-                let divergent_paths = origin_as_paths.find_divergent_paths();
-                println!("{:#?}", divergent_paths);
-                if true {
-                    break;
-                }
-            }
+            let mut divergent_paths: Vec<DivergentPaths> = self
+                .as_paths
+                .par_iter()
+                .filter(|(_, origin_as_paths)| origin_as_paths.len() > 1)
+                .flat_map_iter(|(_, origin_as_paths)| origin_as_paths.find_divergent_paths())
+                .collect();
+            divergent_paths.sort_by_key(|d| (d.origin, d.shared_asn));
+            info!("Found {} divergent paths", divergent_paths.len());
+            divergent_paths
         }
 
-        fn get_as_paths(&self) -> Values<'_, Asn, OriginAsPaths> {
+        pub fn get_as_paths(&self) -> Values<'_, Asn, OriginAsPaths> {
             self.as_paths.values()
         }
 
@@ -87,6 +107,26 @@ pub mod path_data {
             total
         }
 
+        pub fn get_as_set_routes(&self) -> &IndexSet<Route> {
+            &self.as_set_routes
+        }
+
+        /// Every route, the routes grouped by origin followed by the AS set routes
+        pub fn get_routes(&self) -> impl Iterator<Item = &Route> {
+            self.get_as_paths()
+                .flat_map(|origin_as_paths| origin_as_paths.get_as_paths())
+                .flat_map(|as_path| as_path.get_routes())
+                .chain(&self.as_set_routes)
+        }
+
+        /// All routes, including AS set routes (see get_routes())
+        pub fn get_routes_count(&self) -> usize {
+            self.get_as_paths()
+                .map(|origin_as_paths| origin_as_paths.get_routes_count())
+                .sum::<usize>()
+                + self.as_set_routes.len()
+        }
+
         fn get_as_paths_for_origin(&self, origin: &Asn) -> &OriginAsPaths {
             if self.has_as_paths_for_origin(origin) {
                 self.as_paths.get(origin).unwrap()
@@ -103,10 +143,6 @@ pub mod path_data {
             }
         }
 
-        fn get_origins(&self) -> Keys<'_, Asn, OriginAsPaths> {
-            self.as_paths.keys()
-        }
-
         pub fn get_origins_count(&self) -> usize {
             self.as_paths.len()
         }
@@ -130,115 +166,56 @@ pub mod path_data {
 
         pub fn insert_route(&mut self, route: Route) {
             debug!("Adding route {:#?}", route);
-            if !self.has_route(&route) {
+            if route.has_as_sets() {
+                self.as_set_routes.insert(route);
+            } else if !self.has_route(&route) {
                 self.add_origin(*route.get_origin());
-                self.add_as_path(route.get_as_path().clone());
+                self.add_as_path(route.get_deduped_as_path().clone());
                 self.add_route(route);
             }
         }
 
-        /// Copy all origins and their AS paths from other to self
-        pub fn merge_from(&mut self, other: &Self) {
-            for origin in other.get_origins() {
-                self.add_origin_as_paths(origin, other.get_as_paths_for_origin(origin));
-            }
-        }
-
-        /// Move all origins and their AS paths from other to self
+        /// Move all origins, their AS paths and routes from other to self, leaving other
+        /// empty. AS paths seen by both are kept once, with the routes from both.
+        /// The smaller of the two is always merged into the larger.
         pub fn move_from(&mut self, other: &mut Self) {
-            let origins: Vec<Asn> = other.get_origins().cloned().collect();
-
-            for origin in origins {
-                self.add_origin_as_paths(&origin, &other.pop_as_paths_for_origin(&origin));
+            if self.as_paths.len() < other.as_paths.len() {
+                std::mem::swap(self, other);
             }
-        }
 
-        /// Merge pairs of PathData objs, delete the 2nd object from each pair,
-        /// then merge the remaining objs in pairs. Continue until only one obj is left.
-        pub fn merge_path_data(mut all_path_data: Vec<PathData>) -> PathData {
-            info!("Merging {} objects", all_path_data.len());
-            let origins: usize = all_path_data.iter().map(|d| d.get_origins_count()).sum();
-            let as_paths: usize = all_path_data.iter().map(|d| d.get_as_paths_count()).sum();
-            info!("Pre-merge, {} origins, {} AS paths", origins, as_paths);
-
-            if all_path_data.is_empty() {
-                panic!("No sequences to merge!");
-            } else if all_path_data.len() == 1 {
-                debug!("Only 1 item, nothing to merge");
-                return all_path_data.pop().unwrap();
+            for (_, origin_as_paths) in other.as_paths.drain() {
+                self.insert_origin_as_paths(origin_as_paths);
             }
-
-            // Merge pairs of PathData objects then loop over all_path_data and delete every 2nd object.
-            // Repeat until there is only one object left.
-            while all_path_data.len() > 1 {
-                debug!("{} item(s) left to merge", all_path_data.len());
-
-                for chunks in all_path_data.chunks_mut(2) {
-                    if let [seq1, seq2] = chunks {
-                        seq1.move_from(seq2);
-                    }
-                }
-
-                let to_delete = all_path_data.len() / 2; // rounds down
-                let mut deleted = 0;
-                let mut index = 1;
-
-                while deleted < to_delete {
-                    for i in 0..all_path_data.len() {
-                        // ^ Up to but not including
-
-                        if i == index {
-                            all_path_data.remove(i);
-                            deleted += 1;
-                            index += 1;
-                            break;
-                        }
-                    }
-                }
-            }
-
-            assert!(all_path_data.len() == 1);
-            let path_data = all_path_data.pop().unwrap();
-            info!(
-                "Post-merge, {} origins, {} AS paths",
-                path_data.get_origins_count(),
-                path_data.get_as_paths_count()
-            );
-
-            path_data
-        }
-
-        pub fn pop_as_paths_for_origin(&mut self, origin: &Asn) -> OriginAsPaths {
-            self.as_paths.remove(origin).unwrap()
+            self.as_set_routes.extend(other.as_set_routes.drain(..));
         }
 
-        fn remove_as_paths_for_origin(&mut self, origin: &Asn) {
-            if self.has_as_paths_for_origin(origin) {
-                debug!("Removing AS paths for origin {}", origin);
-                self.as_paths.remove(origin);
-            } else {
-                panic!(
-                    "Attempt to remove AS paths for non-existing origin {}",
-                    origin
-                );
+        /// Move the AS paths and routes for an origin into self, merging them with
+        /// any AS paths which already exist for the origin
+        pub fn insert_origin_as_paths(&mut self, origin_as_paths: OriginAsPaths) {
+            match self.as_paths.entry(*origin_as_paths.get_origin()) {
+                Entry::Occupied(existing) => existing.into_mut().move_from(origin_as_paths),
+                Entry::Vacant(vacant) => {
+                    vacant.insert(origin_as_paths);
+                }
             }
         }
 
-        /// Remove origins which only have a single AS path
+        /// Remove origins which only have a single AS path (or none left)
         pub fn remove_origins_with_single_as_path(&mut self) {
             info!("Removing origins with only one AS path");
 
-            let mut to_remove = Vec::new();
-            for origin in self.get_origins() {
-                if self.get_as_paths_for_origin(origin).len() == 1 {
-                    to_remove.push(origin.to_owned());
+            let before = self.as_paths.len();
+            self.as_paths.retain(|origin, origin_as_paths| {
+                if origin_as_paths.len() > 1 {
+                    return true;
                 }
-            }
-
-            debug!("Removing {} origins with single AS path", to_remove.len(),);
-            for origin in to_remove.iter() {
-                self.remove_as_paths_for_origin(origin);
-            }
+                debug!("Removing AS paths for origin {}", origin);
+                false
+            });
+            debug!(
+                "Removed {} origins with single AS path",
+                before - self.as_paths.len()
+            );
 
             info!(
                 "Remaining multi-path origins {}, with {} AS paths",
@@ -251,7 +228,7 @@ pub mod path_data {
         pub fn remove_single_hop_as_paths(&mut self) {
             info!("Removing single-hop AS paths");
 
-            for mut origin_as_paths in self.get_as_paths_mut() {
+            for origin_as_paths in self.get_as_paths_mut() {
                 origin_as_paths.remove_single_hop_paths();
             }
 
@@ -262,4 +239,194 @@ pub mod path_data {
             );
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::net::Ipv4Addr;
+        use std::time::{Duration, Instant};
+
+        /// The PathData from parsing one file of collector rrc00, in which one peer
+        /// announces three prefixes. Two of the routes have the same deduped AS path.
+        fn get_file_path_data(filename: &str, peer_id: u32) -> PathData {
+            let mut path_data = PathData::new();
+            for (as_path, prefix) in [
+                ([1, 2, 3].as_slice(), "198.51.100.0/24"),
+                (&[1, 2, 2, 3], "198.51.101.0/24"),
+                (&[4, 5, 6], "198.51.102.0/24"),
+            ] {
+                path_data.insert_route(
+                    Route::mock(None)
+                        .with_as_path(as_path)
+                        .with_filename(filename)
+                        .with_collector("rrc00")
+                        .with_peer(peer_id)
+                        .with_prefix(prefix)
+                        .build(),
+                );
+            }
+            path_data
+        }
+
+        fn get_path_routes_count(path_data: &PathData, origin: u32) -> usize {
+            path_data
+                .get_as_paths_for_origin(&Asn::new_32bit(origin))
+                .get_routes_count()
+        }
+
+        #[test]
+        fn test_move_from_merges_as_paths() {
+            let mut path_data = get_file_path_data("rib.a", 1);
+            assert_eq!(path_data.get_origins_count(), 2);
+            assert_eq!(path_data.get_as_paths_count(), 2);
+            assert_eq!(path_data.get_routes_count(), 3);
+
+            // Another file, from another peer, with the same AS paths
+            path_data.move_from(&mut get_file_path_data("rib.b", 2));
+            assert_eq!(path_data.get_origins_count(), 2);
+            assert_eq!(path_data.get_as_paths_count(), 2);
+            assert_eq!(path_data.get_routes_count(), 6);
+            assert_eq!(get_path_routes_count(&path_data, 3), 4);
+            assert_eq!(get_path_routes_count(&path_data, 6), 2);
+
+            // The same routes again, from another file of the same collector
+            path_data.move_from(&mut get_file_path_data("rib.c", 1));
+            assert_eq!(path_data.get_as_paths_count(), 2);
+            assert_eq!(path_data.get_routes_count(), 6);
+        }
+
+        #[test]
+        fn test_move_from_keeps_snapshots_apart() {
+            let mut path_data = get_file_path_data("ris.rrc00.bview.20250922.0800.gz", 1);
+            assert_eq!(path_data.get_routes_count(), 3);
+
+            // The same routes from a later snapshot of the same collector
+            path_data.move_from(&mut get_file_path_data(
+                "ris.rrc00.bview.20250922.1600.gz",
+                1,
+            ));
+            assert_eq!(path_data.get_as_paths_count(), 2);
+            assert_eq!(path_data.get_routes_count(), 6);
+            assert_eq!(get_path_routes_count(&path_data, 3), 4);
+        }
+
+        #[test]
+        fn test_remove_single_hop_and_single_path_origins() {
+            let mut path_data = get_file_path_data("rib.a", 1);
+            for (as_path, prefix) in [
+                ([7].as_slice(), "198.51.103.0/24"),
+                (&[8, 9], "198.51.104.0/24"),
+                (&[9], "198.51.105.0/24"),
+            ] {
+                path_data.insert_route(
+                    Route::mock(None)
+                        .with_as_path(as_path)
+                        .with_filename("rib.a")
+                        .with_collector("rrc00")
+                        .with_peer(1)
+                        .with_prefix(prefix)
+                        .build(),
+                );
+            }
+            assert_eq!(path_data.get_origins_count(), 4);
+            assert_eq!(path_data.get_as_paths_count(), 5);
+
+            // Origin 7 is left with no AS paths and origin 9 with one
+            path_data.remove_single_hop_as_paths();
+            assert_eq!(path_data.get_origins_count(), 4);
+            assert_eq!(path_data.get_as_paths_count(), 3);
+
+            path_data.remove_origins_with_single_as_path();
+            assert_eq!(path_data.get_origins_count(), 0);
+
+            // Only origins with two or more multi-hop AS paths are kept
+            let mut path_data = get_file_path_data("rib.a", 1);
+            path_data.insert_route(
+                Route::mock(None)
+                    .with_as_path(&[4, 5, 7, 6])
+                    .with_filename("rib.a")
+                    .with_collector("rrc00")
+                    .with_peer(1)
+                    .with_prefix("198.51.106.0/24")
+                    .build(),
+            );
+            path_data.remove_single_hop_as_paths();
+            path_data.remove_origins_with_single_as_path();
+            assert_eq!(path_data.get_origins_count(), 1);
+            assert_eq!(get_path_routes_count(&path_data, 6), 2);
+        }
+
+        #[test]
+        fn test_move_from_keeps_routes() {
+            let mut path_data = get_file_path_data("rib.a", 1);
+            let mut other = get_file_path_data("rib.b", 2);
+            path_data.move_from(&mut other);
+            assert_eq!(other.get_origins_count(), 0);
+            assert_eq!(path_data.get_origins_count(), 2);
+            assert_eq!(path_data.get_as_paths_count(), 2);
+            assert_eq!(path_data.get_routes_count(), 6);
+
+            // Moving into an empty PathData
+            let mut empty = PathData::new();
+            empty.move_from(&mut path_data);
+            assert_eq!(path_data.get_origins_count(), 0);
+            assert_eq!(empty.get_routes_count(), 6);
+        }
+
+        /// A synthetic table of peers * prefixes routes. Most origins announce a handful of
+        /// prefixes, and one hyper-connected origin announces a quarter of all prefixes,
+        /// so it ends up with a very large number of routes and AS paths.
+        fn get_synthetic_routes(peers: u32, prefixes: u32) -> Vec<Route> {
+            let mut routes = Vec::with_capacity((peers * prefixes) as usize);
+            for peer_id in 0..peers {
+                let peer_asn = 1000 + peer_id;
+                for p in 0..prefixes {
+                    let origin = if p % 4 == 0 { 13335 } else { 100_000 + p / 5 };
+                    routes.push(
+                        Route::mock(None)
+                            .with_as_path(&[
+                                peer_asn,
+                                10 + (p + peer_id) % 13,
+                                50 + p % 101,
+                                origin,
+                            ])
+                            .with_filename("synthetic")
+                            .with_peer(peer_asn)
+                            .with_prefix(&format!("{}/24", Ipv4Addr::from((p + 1) << 8)))
+                            .with_communities(Vec::new())
+                            .with_large_communities(Vec::new())
+                            .build(),
+                    );
+                }
+            }
+            routes
+        }
+
+        fn time_insert_routes(routes: Vec<Route>) -> (Duration, PathData) {
+            let mut path_data = PathData::new();
+            let start = Instant::now();
+            for route in routes {
+                path_data.insert_route(route);
+            }
+            (start.elapsed(), path_data)
+        }
+
+        /// Run with: cargo test --release bench_insert_route -- --ignored --nocapture
+        /// Inserting a route is a hash lookup, so 4x the routes should take roughly 4x the
+        /// time, the previous linear scans took ~16x.
+        #[test]
+        #[ignore]
+        fn bench_insert_route() {
+            let (small, _) = time_insert_routes(get_synthetic_routes(20, 25_000));
+            let (large, path_data) = time_insert_routes(get_synthetic_routes(20, 100_000));
+            println!(
+                "Inserted 500k routes in {:?}, 2M routes in {:?} ({} origins, {} AS paths)",
+                small,
+                large,
+                path_data.get_origins_count(),
+                path_data.get_as_paths_count()
+            );
+            assert!(large < small * 8);
+        }
+    }
 }
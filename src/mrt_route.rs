@@ -1,102 +1,442 @@
 pub mod route {
+    use crate::mrt_aggregator::aggregator::Aggregator;
+    use crate::mrt_as_path_segments::as_path_segments::{
+        ConfedSegment, PositionedSegment, RawAsPaths,
+    };
     use crate::mrt_asn::asn::Testing as AsnTesting;
-    //////// use crate::mrt_community::communities::get_mock as get_mock_communities;
+    use crate::mrt_community::communities::get_mock as get_mock_communities;
+    use crate::mrt_community::shared_communities::SharedCommunities;
+    use crate::mrt_interner::interner::{
+        AsPathId, CollectorId, FilenameId, PeerId, RawAsPathsId, SnapshotId,
+    };
     use crate::mrt_ip_addr::ip_addr::Testing as IpAddrTesting;
     use crate::mrt_ip_net::ip_net::Testing as IpNetTesting;
-    //////// use crate::mrt_large_community::large_communities::get_mock as get_mock_large_communities;
+    use crate::mrt_large_community::large_communities::get_mock as get_mock_large_communities;
+    use crate::mrt_large_community::shared_large_communities::SharedLargeCommunities;
     use crate::mrt_peer::peer::Testing as PeerTesting;
+    use crate::ribs::rib_getter::{
+        RibFile, get_collector_from_filename, get_snapshot_from_filename,
+    };
     use bgpkit_parser::models::{Asn, Community, LargeCommunity, Peer};
     use ipnet::IpNet;
     use std::hash::Hash;
-    use std::net::IpAddr;
+    use std::net::{IpAddr, Ipv4Addr};
 
-    /// Store a route pulled from an MRT file (one route object per prefix)
+    /// The MRT file a route was read from. This is the same for every route in a file,
+    /// so it's interned once per file rather than once per route.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct RouteSource {
+        pub filename: FilenameId,
+        /// The same route seen by two collectors (e.g. from a peer of both) is two routes
+        pub collector: CollectorId,
+        /// The same route in two snapshots (e.g. 08:00 and 16:00) is two routes,
+        /// so snapshots can be compared
+        pub snapshot: SnapshotId,
+    }
+
+    impl RouteSource {
+        pub fn new(filename: &str, collector: &str, snapshot: &str) -> Self {
+            RouteSource {
+                filename: FilenameId::new(filename),
+                collector: CollectorId::new(collector),
+                snapshot: SnapshotId::new(snapshot),
+            }
+        }
+
+        /// The collector and snapshot are taken from the filename, see
+        /// get_collector_from_filename() and get_snapshot_from_filename()
+        pub fn from_filename(filename: &str) -> Self {
+            Self::new(
+                filename,
+                get_collector_from_filename(filename),
+                &get_snapshot_from_filename(filename),
+            )
+        }
+
+        pub fn from_rib_file(rib_file: &RibFile) -> Self {
+            Self::new(
+                &rib_file.filename,
+                &rib_file.collector_id,
+                &rib_file.snapshot,
+            )
+        }
+    }
+
+    /// Store a route pulled from an MRT file (one route object per prefix).
+    /// The AS paths (raw and merged), filename and peer are interned, as they are
+    /// shared by many routes.
     #[derive(Clone, Debug, Eq)]
     pub struct Route {
-        as_path: Vec<Asn>,
-        filename: String,
+        /// The full AS path, including any prepending
+        as_path: AsPathId,
+        /// The AS path with prepending removed, routes are grouped by this into an AsPath
+        deduped_as_path: AsPathId,
+        confed_segments: Vec<ConfedSegment>,
+        /// AS sets are kept as received, rather than expanded into the AS path,
+        /// so the path is only the AS sequence
+        as_sets: Vec<PositionedSegment>,
+        /// Only stored when both AS_PATH and AS4_PATH were received, otherwise as_path
+        /// is the raw AS_PATH (or AS4_PATH)
+        raw_as_paths: Option<RawAsPathsId>,
+        /// Only the collector and snapshot are part of the route's identity, not the filename
+        source: RouteSource,
         next_hop: IpAddr,
-        peer: Peer,
+        peer: PeerId,
         prefix: IpNet,
-        // communities: Vec<Community>,
-        // large_communities: Vec<LargeCommunity>,
+        /// Interned, routes with the same communities share one list
+        communities: SharedCommunities,
+        large_communities: SharedLargeCommunities,
+        aggregator: Option<Aggregator>,
     }
 
     impl PartialEq for Route {
         fn eq(&self, other: &Self) -> bool {
             (self.as_path == other.as_path)
+                && (self.confed_segments == other.confed_segments)
+                && (self.as_sets == other.as_sets)
                 && (self.next_hop == other.next_hop)
                 && (self.peer == other.peer)
                 && (self.prefix == other.prefix)
-            // && (self.filename == other.filename)
-            // && (self.communities == other.communities)
-            // && (self.large_communities == other.large_communities)
+                && (self.communities == other.communities)
+                && (self.large_communities == other.large_communities)
+                && (self.aggregator == other.aggregator)
+                && (self.source.collector == other.source.collector)
+                && (self.source.snapshot == other.source.snapshot)
         }
     }
 
+    /// Must hash the same fields as PartialEq compares, filename and raw_as_paths are
+    /// excluded, so routes from several files of the same collector and snapshot are merged
     impl Hash for Route {
         fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
             self.as_path.hash(state);
-            self.filename.hash(state);
+            self.confed_segments.hash(state);
+            self.as_sets.hash(state);
             self.next_hop.hash(state);
             self.peer.hash(state);
             self.prefix.hash(state);
+            self.communities.hash(state);
+            self.large_communities.hash(state);
+            self.aggregator.hash(state);
+            self.source.collector.hash(state);
+            self.source.snapshot.hash(state);
         }
     }
 
     impl Route {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             as_path: Vec<Asn>,
-            filename: String,
+            confed_segments: Vec<ConfedSegment>,
+            as_sets: Vec<PositionedSegment>,
+            raw_as_paths: Option<RawAsPaths>,
+            source: RouteSource,
             next_hop: IpAddr,
             peer: Peer,
             prefix: IpNet,
-            // communities: Vec<Community>,
-            // large_communities: Vec<LargeCommunity>,
+            communities: SharedCommunities,
+            large_communities: SharedLargeCommunities,
+            aggregator: Option<Aggregator>,
+        ) -> Self {
+            let mut deduped_as_path = as_path.clone();
+            deduped_as_path.dedup();
+            Self::from_interned(
+                AsPathId::new(as_path),
+                AsPathId::new(deduped_as_path),
+                confed_segments,
+                as_sets,
+                raw_as_paths.map(RawAsPathsId::new),
+                source,
+                next_hop,
+                PeerId::new(peer),
+                prefix,
+                communities,
+                large_communities,
+                aggregator,
+            )
+        }
+
+        /// Build a route from values which are already interned (e.g. loaded from a
+        /// snapshot), deduped_as_path must be as_path with prepending removed
+        #[allow(clippy::too_many_arguments)]
+        pub fn from_interned(
+            as_path: AsPathId,
+            deduped_as_path: AsPathId,
+            confed_segments: Vec<ConfedSegment>,
+            as_sets: Vec<PositionedSegment>,
+            raw_as_paths: Option<RawAsPathsId>,
+            source: RouteSource,
+            next_hop: IpAddr,
+            peer: PeerId,
+            prefix: IpNet,
+            communities: SharedCommunities,
+            large_communities: SharedLargeCommunities,
+            aggregator: Option<Aggregator>,
         ) -> Self {
             Self {
                 as_path,
-                filename,
+                deduped_as_path,
+                confed_segments,
+                as_sets,
+                raw_as_paths,
+                source,
                 next_hop,
                 peer,
                 prefix,
-                // communities,
-                // large_communities,
+                communities,
+                large_communities,
+                aggregator,
             }
         }
 
         pub fn get_mock(origin: Option<Asn>) -> Route {
-            let as_path = Vec::from([
-                Asn::get_mock(Some(1)),
-                Asn::get_mock(Some(2)),
-                origin.unwrap_or(Asn::get_mock(None)),
-            ]);
+            Self::mock(origin).build()
+        }
 
-            Route {
-                as_path,
+        /// A builder for test routes, starting from the get_mock() values
+        pub fn mock(origin: Option<Asn>) -> MockRoute {
+            MockRoute {
+                as_path: Vec::from([
+                    Asn::get_mock(Some(1)),
+                    Asn::get_mock(Some(2)),
+                    origin.unwrap_or(Asn::get_mock(None)),
+                ]),
+                confed_segments: Vec::new(),
+                as_set: Vec::new(),
+                raw_as_paths: None,
                 filename: String::from("unit test"),
+                collector: None,
                 next_hop: IpAddr::get_mock(),
                 peer: Peer::get_mock(),
                 prefix: IpNet::get_mock(),
-                // communities: get_mock_communities(None),
-                // large_communities: get_mock_large_communities(None),
+                communities: get_mock_communities(None),
+                large_communities: get_mock_large_communities(None),
+                aggregator: None,
             }
         }
 
+        pub fn get_aggregator(&self) -> &Option<Aggregator> {
+            &self.aggregator
+        }
+
         pub fn get_as_path(&self) -> &Vec<Asn> {
-            &self.as_path
+            self.as_path.get()
+        }
+
+        pub fn get_as_path_id(&self) -> AsPathId {
+            self.as_path
+        }
+
+        pub fn get_deduped_as_path(&self) -> &Vec<Asn> {
+            self.deduped_as_path.get()
+        }
+
+        pub fn get_deduped_as_path_id(&self) -> AsPathId {
+            self.deduped_as_path
+        }
+
+        /// The number of times each hop of the deduped AS path appears in the raw AS path:
+        /// [1, 2, 2, 3, 3, 3] -> [1, 2, 3]
+        pub fn get_prepend_counts(&self) -> Vec<usize> {
+            self.get_as_path()
+                .chunk_by(|a, b| a == b)
+                .map(|hop| hop.len())
+                .collect()
+        }
+
+        pub fn get_as_sets(&self) -> &Vec<PositionedSegment> {
+            &self.as_sets
+        }
+
+        pub fn get_confed_segments(&self) -> &Vec<ConfedSegment> {
+            &self.confed_segments
+        }
+
+        pub fn get_communities(&self) -> &[Community] {
+            self.communities.get_communities()
+        }
+
+        pub fn get_shared_communities(&self) -> &SharedCommunities {
+            &self.communities
+        }
+
+        pub fn get_large_communities(&self) -> &[LargeCommunity] {
+            self.large_communities.get_large_communities()
+        }
+
+        pub fn get_shared_large_communities(&self) -> &SharedLargeCommunities {
+            &self.large_communities
+        }
+
+        pub fn get_filename(&self) -> &'static String {
+            self.source.filename.get()
+        }
+
+        pub fn get_collector(&self) -> &'static String {
+            self.source.collector.get()
         }
 
-        // pub fn get_communities(&self) -> &Vec<Community> {
-        //     &self.communities
-        // }
+        pub fn get_snapshot(&self) -> &'static String {
+            self.source.snapshot.get()
+        }
+
+        pub fn get_source(&self) -> RouteSource {
+            self.source
+        }
 
-        // pub fn get_large_communities(&self) -> &Vec<LargeCommunity> {
-        //     &self.large_communities
-        // }
+        pub fn get_next_hop(&self) -> &IpAddr {
+            &self.next_hop
+        }
 
+        /// The last ASN of the AS path. For AS set routes this is the last ASN of the
+        /// AS sequence (usually the aggregator), which is missing if the path is only a set.
         pub fn get_origin(&self) -> &Asn {
-            self.as_path.last().unwrap()
+            self.get_as_path().last().unwrap()
+        }
+
+        pub fn get_peer(&self) -> &Peer {
+            self.peer.get()
+        }
+
+        pub fn get_peer_id(&self) -> PeerId {
+            self.peer
+        }
+
+        pub fn get_prefix(&self) -> &IpNet {
+            &self.prefix
+        }
+
+        pub fn get_raw_as_paths(&self) -> Option<&'static RawAsPaths> {
+            self.raw_as_paths.map(|raw_as_paths| raw_as_paths.get())
+        }
+
+        pub fn has_as_sets(&self) -> bool {
+            !self.as_sets.is_empty()
+        }
+
+        pub fn has_prepending(&self) -> bool {
+            self.as_path != self.deduped_as_path
+        }
+
+        /// AS_TRANS survived the AS4_PATH merge, so a 4-byte ASN is hidden in the path
+        pub fn has_as_trans(&self) -> bool {
+            self.get_as_path().contains(&Asn::TRANSITION)
+        }
+    }
+
+    /// Builds a route for tests, only the fields a test cares about need to be set
+    #[derive(Debug)]
+    pub struct MockRoute {
+        as_path: Vec<Asn>,
+        confed_segments: Vec<ConfedSegment>,
+        as_set: Vec<Asn>,
+        raw_as_paths: Option<RawAsPaths>,
+        filename: String,
+        /// Taken from the filename if not set
+        collector: Option<String>,
+        next_hop: IpAddr,
+        peer: Peer,
+        prefix: IpNet,
+        communities: Vec<Community>,
+        large_communities: Vec<LargeCommunity>,
+        aggregator: Option<Aggregator>,
+    }
+
+    impl MockRoute {
+        pub fn with_as_path(mut self, as_path: &[u32]) -> Self {
+            self.as_path = as_path.iter().map(|a| Asn::new_32bit(*a)).collect();
+            self
+        }
+
+        /// An AS set after the AS sequence, leaving the set empty means no AS set
+        pub fn with_as_set(mut self, as_set: &[u32]) -> Self {
+            self.as_set = as_set.iter().map(|a| Asn::new_32bit(*a)).collect();
+            self
+        }
+
+        pub fn with_confed_segments(mut self, confed_segments: Vec<ConfedSegment>) -> Self {
+            self.confed_segments = confed_segments;
+            self
+        }
+
+        pub fn with_raw_as_paths(mut self, raw_as_paths: RawAsPaths) -> Self {
+            self.raw_as_paths = Some(raw_as_paths);
+            self
+        }
+
+        pub fn with_filename(mut self, filename: &str) -> Self {
+            self.filename = String::from(filename);
+            self
+        }
+
+        pub fn with_collector(mut self, collector: &str) -> Self {
+            self.collector = Some(String::from(collector));
+            self
+        }
+
+        /// A peer with the given ASN, whose IP, BGP ID and next-hop are 192.0.2.<peer_asn>
+        pub fn with_peer(mut self, peer_asn: u32) -> Self {
+            let peer_ip = Ipv4Addr::new(192, 0, 2, peer_asn as u8);
+            self.next_hop = IpAddr::V4(peer_ip);
+            self.peer = Peer::new(peer_ip, IpAddr::V4(peer_ip), Asn::new_32bit(peer_asn));
+            self
+        }
+
+        pub fn with_prefix(mut self, prefix: &str) -> Self {
+            self.prefix = prefix
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid prefix {}: {}", prefix, e));
+            self
+        }
+
+        pub fn with_communities(mut self, communities: Vec<Community>) -> Self {
+            self.communities = communities;
+            self
+        }
+
+        pub fn with_large_communities(mut self, large_communities: Vec<LargeCommunity>) -> Self {
+            self.large_communities = large_communities;
+            self
+        }
+
+        /// Aggregated by the given ASN, on router 192.0.2.1
+        pub fn with_aggregator(mut self, asn: u32) -> Self {
+            self.aggregator = Some(Aggregator {
+                asn: Asn::new_32bit(asn),
+                ip: Ipv4Addr::new(192, 0, 2, 1),
+            });
+            self
+        }
+
+        pub fn build(self) -> Route {
+            let as_sets = if self.as_set.is_empty() {
+                Vec::new()
+            } else {
+                Vec::from([PositionedSegment {
+                    position: self.as_path.len(),
+                    asns: self.as_set,
+                }])
+            };
+            Route::new(
+                self.as_path,
+                self.confed_segments,
+                as_sets,
+                self.raw_as_paths,
+                match &self.collector {
+                    Some(collector) => RouteSource::new(
+                        &self.filename,
+                        collector,
+                        &get_snapshot_from_filename(&self.filename),
+                    ),
+                    None => RouteSource::from_filename(&self.filename),
+                },
+                self.next_hop,
+                self.peer,
+                self.prefix,
+                SharedCommunities::new(self.communities),
+                SharedLargeCommunities::new(self.large_communities),
+                self.aggregator,
+            )
         }
     }
 }
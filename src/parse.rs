@@ -1,31 +1,92 @@
 pub mod rib_parser {
+    use crate::mrt_aggregator::aggregator::Aggregator;
+    use crate::mrt_as_path_segments::as_path_segments::{
+        AsPathSegments, RawAsPaths, merge_as4_path,
+    };
+    use crate::mrt_community::shared_communities::SharedCommunities;
+    use crate::mrt_large_community::shared_large_communities::SharedLargeCommunities;
     use crate::mrt_paths::path_data::PathData;
-    use crate::mrt_route::route::Route;
+    use crate::mrt_route::route::{Route, RouteSource};
+    use crate::reader::mrt_reader::get_reader;
     use crate::ribs::rib_getter::RibFile;
     use bgpkit_parser::models::{
-        AsPathSegment, Asn, AttrFlags, AttrType, Attribute, AttributeValue, Community,
-        LargeCommunity, MrtMessage, Peer, RibAfiEntries, RibEntry, TableDumpV2Message,
-        TableDumpV2Type,
+        Asn, AttrFlags, AttrType, Attribute, AttributeValue, Attributes, Bgp4MpEnum, BgpMessage,
+        BgpState, BgpUpdateMessage, Community, LargeCommunity, MrtMessage, Peer, RibAfiEntries,
+        TableDumpMessage, TableDumpV2Message, TableDumpV2Type,
     };
-    use bgpkit_parser::{BgpkitParser, MrtRecord};
+    use bgpkit_parser::{BgpkitParser, MrtRecord, RawMrtRecord};
     use ipnet::IpNet;
-    use log::{debug, info};
+    use log::{debug, info, warn};
     use rayon::ThreadPoolBuilder;
-    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
     use std::collections::HashMap;
-    use std::net::IpAddr;
-
-    /// Given a list of RIB files, parse them, merge the results, and strip and redundant data
-    pub fn get_path_data(rib_files: &Vec<RibFile>, threads: &u32) -> PathData {
-        let all_mrts_path_data = parse_rib_files(rib_files, threads);
-        let mut merged_path_data = PathData::merge_path_data(all_mrts_path_data);
-        merged_path_data.remove_single_hop_as_paths();
-        merged_path_data.remove_origins_with_single_as_path();
+    use std::io::Read;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// Track the announcements and withdrawals seen in a BGP4MP update file.
+    /// The last AS path announced by each peer for each prefix is kept, so that
+    /// withdrawals (explicit, implicit, or via a session going down) can be matched
+    /// against a previous announcement.
+    /// This is only used for stats, withdrawn routes aren't removed from the PathData:
+    /// it is the union of every AS path seen across all files, and a path which was
+    /// withdrawn was still used while it was active.
+    #[derive(Debug, Default)]
+    struct UpdateTracker {
+        active: HashMap<(IpAddr, IpNet), Vec<Asn>>,
+        announcements: u64,
+        implicit_withdrawals: u64,
+        session_resets: u64,
+        unmatched_withdrawals: u64,
+        withdrawals: u64,
+    }
+
+    impl UpdateTracker {
+        fn announce(&mut self, peer_ip: IpAddr, prefix: IpNet, as_path: &[Asn]) {
+            self.announcements += 1;
+            if let Some(previous) = self.active.insert((peer_ip, prefix), as_path.to_vec())
+                && previous != as_path
+            {
+                self.implicit_withdrawals += 1;
+            }
+        }
+
+        fn withdraw(&mut self, peer_ip: IpAddr, prefix: IpNet) {
+            if self.active.remove(&(peer_ip, prefix)).is_some() {
+                self.withdrawals += 1;
+            } else {
+                self.unmatched_withdrawals += 1;
+            }
+        }
+
+        /// A peer session left the Established state, all of its routes are gone
+        fn reset_peer(&mut self, peer_ip: IpAddr) {
+            let before = self.active.len();
+            self.active.retain(|(ip, _), _| *ip != peer_ip);
+            self.withdrawals += (before - self.active.len()) as u64;
+            self.session_resets += 1;
+        }
+    }
+
+    /// Given a list of RIB files, parse and merge them, and unless filter is false,
+    /// strip the single-hop AS paths and origins with a single AS path, which can't
+    /// diverge. Without filtering the aggregate search sees every origin of a prefix.
+    pub fn get_path_data(
+        rib_files: &[RibFile],
+        threads: &u32,
+        batch_size: &usize,
+        filter: bool,
+    ) -> PathData {
+        let mut merged_path_data = parse_rib_files(rib_files, threads, batch_size);
+        if filter {
+            merged_path_data.remove_single_hop_as_paths();
+            merged_path_data.remove_origins_with_single_as_path();
+        }
         merged_path_data
     }
 
-    /// Spin up a separate tread for each MRT file which needs to be parsed
-    pub fn parse_rib_files(rib_files: &Vec<RibFile>, threads: &u32) -> Vec<PathData> {
+    /// Spin up a separate tread for each MRT file which needs to be parsed,
+    /// the records within each file are also parsed in parallel in batches.
+    pub fn parse_rib_files(rib_files: &[RibFile], threads: &u32, batch_size: &usize) -> PathData {
         info!("Paring {} RIB files", rib_files.len());
         debug!(
             "{:?}",
@@ -40,22 +101,39 @@ pub mod rib_parser {
             .build_global()
             .unwrap();
 
-        let path_data = rib_files
-            .into_par_iter()
-            .map(|rib_file| parse_rib_file(rib_file.filename.clone()))
-            .collect();
+        let path_data = merge_parsed(rib_files, |rib_file| parse_rib_file(rib_file, *batch_size));
 
-        info!("All RIB files parse");
+        info!(
+            "All RIB files parsed, {} origins, {} AS paths",
+            path_data.get_origins_count(),
+            path_data.get_as_paths_count()
+        );
         path_data
     }
 
-    /// Return the mapping of peer IDs to peer details
-    fn get_peer_id_map(fp: &String) -> HashMap<u16, Peer> {
-        let parser = BgpkitParser::new(fp.as_str())
-            .unwrap_or_else(|_| panic!("Unable to parse MRT file {}", fp));
-
-        let mrt_record = parser.into_record_iter().next().unwrap();
+    /// Parse each item in parallel and merge the results.
+    /// Each item is merged into its thread's PathData as soon as it is parsed, and the
+    /// per-thread PathData are then merged, so only one PathData per thread is kept
+    /// rather than one per item.
+    fn merge_parsed<T, F>(items: &[T], parse: F) -> PathData
+    where
+        T: Sync,
+        F: Fn(&T) -> PathData + Sync + Send,
+    {
+        items
+            .par_iter()
+            .fold(PathData::new, |mut path_data, item| {
+                path_data.move_from(&mut parse(item));
+                path_data
+            })
+            .reduce(PathData::new, |mut path_data, mut other| {
+                path_data.move_from(&mut other);
+                path_data
+            })
+    }
 
+    /// Return the mapping of peer IDs to peer details
+    fn get_peer_id_map(mrt_record: &MrtRecord, fp: &String) -> HashMap<u16, Peer> {
         if let MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(peer_table)) =
             &mrt_record.message
         {
@@ -73,21 +151,18 @@ pub mod rib_parser {
         fp: &String,
         count: &u32,
     ) -> Option<&'a RibAfiEntries> {
-        let v4_default: IpNet = "0.0.0.0/0".parse().unwrap();
-        let v6_default: IpNet = "::/0".parse().unwrap();
-
         if let MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(rib_entries)) =
             &mrt_entry.message
         {
             match rib_entries.rib_type {
                 TableDumpV2Type::RibIpv4Unicast | TableDumpV2Type::RibIpv4UnicastAddPath => {
-                    if rib_entries.prefix.prefix == v4_default {
+                    if is_default_route(&rib_entries.prefix.prefix) {
                         return None;
                     }
                     Some(rib_entries)
                 }
                 TableDumpV2Type::RibIpv6Unicast | TableDumpV2Type::RibIpv6UnicastAddPath => {
-                    if rib_entries.prefix.prefix == v6_default {
+                    if is_default_route(&rib_entries.prefix.prefix) {
                         return None;
                     }
                     Some(rib_entries)
@@ -105,42 +180,27 @@ pub mod rib_parser {
         }
     }
 
-    /// Return the next-nop which can be v4 or v6.
-    /// If v6 LL and GUA nh exists, GUA is returned.
-    fn get_next_hop(rib_entry: &RibEntry, fp: &String, count: &u32) -> IpAddr {
-        if rib_entry.attributes.get_reachable_nlri().is_some() {
-            let mp_nlri = rib_entry
-                .attributes
-                .get_reachable_nlri()
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Couldn't extract MP NLRI in file {} ({}) for: {:#?}",
-                        fp, count, rib_entry
-                    )
-                });
-
-            assert!(
-                mp_nlri.is_ipv6(),
-                "MP NLRI is used for non-IPv6 info in file {} ({}): {:#?}",
-                fp,
-                count,
-                rib_entry
+    /// Return the next-hop which can be v4 or v6, of an MP_REACH_NLRI attribute of any AFI
+    /// (e.g. IPv4 prefixes in a BGP4MP update, or a v6 next-hop for IPv4 per RFC 8950),
+    /// otherwise of the NEXT_HOP attribute. If v6 LL and GUA nh exists, GUA is returned.
+    /// Returns None (and warns) if there is no next-hop.
+    fn get_next_hop(attributes: &Attributes, fp: &String, count: &u32) -> Option<IpAddr> {
+        let next_hop = attributes
+            .get_reachable_nlri()
+            .and_then(|mp_nlri| mp_nlri.next_hop)
+            .map(|next_hop| next_hop.addr())
+            .or_else(|| attributes.next_hop());
+        if next_hop.is_none() {
+            warn!(
+                "No next-hop in file {} ({}), skipping: {:#?}",
+                fp, count, attributes
             );
-
-            mp_nlri.next_hop_addr()
-        } else {
-            rib_entry.attributes.next_hop().unwrap_or_else(|| {
-                panic!(
-                    "No next-hop in file {} ({}) for: {:#?}",
-                    fp, count, rib_entry
-                )
-            })
         }
+        next_hop
     }
 
-    fn get_communities(rib_entry: &RibEntry) -> Vec<Community> {
-        if let AttributeValue::Communities(communities) = rib_entry
-            .attributes
+    fn get_communities(attributes: &Attributes) -> Vec<Community> {
+        if let AttributeValue::Communities(communities) = attributes
             .get_attr(AttrType::COMMUNITIES)
             .unwrap_or(Attribute {
                 value: AttributeValue::Communities(Vec::new()),
@@ -154,9 +214,8 @@ pub mod rib_parser {
         }
     }
 
-    fn get_large_communities(rib_entry: &RibEntry) -> Vec<LargeCommunity> {
-        if let AttributeValue::LargeCommunities(large_communities) = rib_entry
-            .attributes
+    fn get_large_communities(attributes: &Attributes) -> Vec<LargeCommunity> {
+        if let AttributeValue::LargeCommunities(large_communities) = attributes
             .get_attr(AttrType::LARGE_COMMUNITIES)
             .unwrap_or(Attribute {
                 value: AttributeValue::LargeCommunities(Vec::new()),
@@ -170,56 +229,141 @@ pub mod rib_parser {
         }
     }
 
-    /// Split the segments of the AS Path into an AS Sequence and an AS Set.
-    /// The likelihood of there being more than on AS Sequnece (because the path)
-    /// is longer than 255 ASNs is incredibly low. Equally the likely of more than
-    /// one AS Set being present is incredily low. So we make the lazy assumption
-    /// in the DFZ we'll see one of each, or one of both.
-    fn get_as_path_chunks(rib_entry: &RibEntry, fp: &String, count: &u32) -> (Vec<Asn>, Vec<Asn>) {
-        let as_path_segments = &rib_entry
-            .attributes
-            .as_path()
-            .unwrap_or_else(|| {
-                panic!(
-                    "Unable to unpack AS Path segments from RIB entry {:#?}",
-                    rib_entry
-                )
-            })
-            .segments;
+    fn get_aggregator_attr(
+        attributes: &Attributes,
+        attr_type: AttrType,
+    ) -> Option<(Asn, Ipv4Addr)> {
+        if let AttributeValue::Aggregator { asn, id, .. } = attributes.get_attr(attr_type)?.value {
+            Some((asn, id))
+        } else {
+            None
+        }
+    }
 
-        let mut as_sequence = Vec::<Asn>::new();
-        let mut as_set = Vec::<Asn>::new();
+    fn get_aggregator(attributes: &Attributes) -> Option<Aggregator> {
+        Aggregator::new(
+            get_aggregator_attr(attributes, AttrType::AGGREGATOR),
+            get_aggregator_attr(attributes, AttrType::AS4_AGGREGATOR),
+        )
+    }
 
-        for path_seg in as_path_segments {
-            if let AsPathSegment::AsSequence(asns) = path_seg {
-                as_sequence = asns.clone();
-            } else if let AsPathSegment::AsSet(asns) = path_seg {
-                as_set = asns.clone();
-            } else {
-                panic!(
-                    "Couldn't extract AS path sequence in file {} ({}): {:#?}",
-                    fp, count, path_seg
+    /// Reconstruct the AS path from all of its segments, in order.
+    /// If an AS4_PATH attribute is present it is merged with AS_PATH (RFC 6793),
+    /// and both raw paths are returned. A record with only AS4_PATH (as written by
+    /// some encoders for 4-byte ASNs) uses AS4_PATH as the path.
+    fn get_as_path_segments(
+        attributes: &Attributes,
+        fp: &String,
+        count: &u32,
+    ) -> (AsPathSegments, Option<RawAsPaths>) {
+        let mut as_path = None;
+        let mut as4_path = None;
+        for value in attributes {
+            if let AttributeValue::AsPath { path, is_as4 } = value {
+                if *is_as4 {
+                    as4_path = Some(path);
+                } else {
+                    as_path = Some(path);
+                }
+            }
+        }
+
+        match (as_path, as4_path) {
+            (Some(as_path), Some(as4_path)) => (
+                AsPathSegments::new(&merge_as4_path(as_path, as4_path)),
+                Some(RawAsPaths {
+                    as_path: as_path.clone(),
+                    as4_path: as4_path.clone(),
+                }),
+            ),
+            (Some(as_path), None) => (AsPathSegments::new(as_path), None),
+            (None, Some(as4_path)) => {
+                debug!(
+                    "AS4_PATH without AS_PATH in file {} ({}), using AS4_PATH",
+                    fp, count
                 );
+                (AsPathSegments::new(as4_path), None)
             }
+            (None, None) => panic!(
+                "Unable to unpack AS Path segments in file {} ({}): {:#?}",
+                fp, count, attributes
+            ),
         }
+    }
 
-        if as_sequence.is_empty() && !as_set.is_empty() {
-            panic!(
-                "AS set defined without an AS sequence in file {} ({}): {:#?}",
-                fp, count, rib_entry
+    /// Build the route for a single prefix from its path attributes and insert it.
+    /// All AS sequence segments are concatenated in order to form the AS path. AS sets
+    /// and confederation segments are stored on the route with their position in the
+    /// AS path, routes with an AS set are kept apart from the per-origin AS paths.
+    fn insert_routes(
+        path_data: &mut PathData,
+        attributes: &Attributes,
+        peer: Peer,
+        prefix: IpNet,
+        source: RouteSource,
+        count: &u32,
+    ) -> Option<Vec<Asn>> {
+        let fp = source.filename.get();
+        let next_hop = get_next_hop(attributes, fp, count)?;
+
+        let (segments, raw_as_paths) = get_as_path_segments(attributes, fp, count);
+
+        if segments.is_empty() {
+            debug!(
+                "Skipping empty AS sequence and empty AS set, assuming iBGP path in file {} ({}): {:#?}",
+                fp, count, attributes
+            );
+            return None;
+        }
+
+        if segments.has_confed_segments() {
+            debug!(
+                "Confederation segments in file {} ({}): {:#?}",
+                fp,
+                count,
+                segments.get_confed_segments()
             );
         }
 
-        (as_sequence, as_set)
+        let communities = SharedCommunities::new(get_communities(attributes));
+        let large_communities = SharedLargeCommunities::new(get_large_communities(attributes));
+
+        let as_sets = segments
+            .get_as_sets()
+            .iter()
+            .filter(|as_set| !as_set.asns.is_empty())
+            .cloned()
+            .collect();
+
+        path_data.insert_route(Route::new(
+            segments.get_as_sequence().clone(),
+            segments.get_confed_segments().clone(),
+            as_sets,
+            raw_as_paths,
+            source,
+            next_hop,
+            peer,
+            prefix,
+            communities,
+            large_communities,
+            get_aggregator(attributes),
+        ));
+
+        Some(segments.get_as_sequence().clone())
+    }
+
+    fn is_default_route(prefix: &IpNet) -> bool {
+        prefix.prefix_len() == 0
     }
 
     fn parse_rib_entries(
         mrt_entry: &MrtRecord,
         path_data: &mut PathData,
         id_peer_map: &HashMap<u16, Peer>,
-        fp: &String,
+        source: RouteSource,
         count: &u32,
     ) {
+        let fp = source.filename.get();
         let rib_entries = get_rib_entries(mrt_entry, fp, count);
         if rib_entries.is_none() {
             return;
@@ -232,74 +376,255 @@ pub mod rib_parser {
         });
 
         for rib_entry in &rib_entries.rib_entries {
-            let next_hop = get_next_hop(rib_entry, fp, count);
-            //////// let communities = get_communities(rib_entry);
-            //////// let large_communities = get_large_communities(rib_entry);
+            insert_routes(
+                path_data,
+                &rib_entry.attributes,
+                id_peer_map[&rib_entry.peer_index],
+                rib_entries.prefix.prefix,
+                source,
+                count,
+            );
+        }
+    }
 
-            // Split each AS path segment into an AsSequence and AsSet.
-            // If an AsSet is defined, for each ASN in the set, create a unique AS path
-            // (the AS Sequence + the AsSet ASN) and record the prefix as being available
-            // via multiple AS Paths.
-            let (as_sequence, as_set) = get_as_path_chunks(rib_entry, fp, count);
+    /// Legacy TABLE_DUMP (v1) records hold one route per record and have no
+    /// peer index table, the peer details are taken from each record instead.
+    /// No BGP ID is available for the peer so the unspecified address is used.
+    fn parse_table_dump_entry(
+        table_dump: &TableDumpMessage,
+        path_data: &mut PathData,
+        source: RouteSource,
+        count: &u32,
+    ) {
+        if is_default_route(&table_dump.prefix.prefix) {
+            return;
+        }
 
-            if as_sequence.is_empty() && as_set.is_empty() {
-                debug!(
-                    "Skipping empty AS sequence and empty AS set, assuming iBGP path in file {} ({}): {:#?}",
-                    fp, count, rib_entry
-                );
-                continue;
-            }
+        insert_routes(
+            path_data,
+            &table_dump.attributes,
+            Peer::new(
+                Ipv4Addr::UNSPECIFIED,
+                table_dump.peer_ip,
+                table_dump.peer_asn,
+            ),
+            table_dump.prefix.prefix,
+            source,
+            count,
+        );
+    }
+
+    /// Return the announced and withdrawn prefixes in a BGP UPDATE.
+    /// Prefixes may be in the legacy NLRI/withdrawn fields (v4) or in the
+    /// MP_REACH_NLRI/MP_UNREACH_NLRI attributes (v6).
+    fn get_update_prefixes(update: &BgpUpdateMessage) -> (Vec<IpNet>, Vec<IpNet>) {
+        let mut announced: Vec<IpNet> =
+            update.announced_prefixes.iter().map(|p| p.prefix).collect();
+        if let Some(mp_nlri) = update.attributes.get_reachable_nlri() {
+            announced.extend(mp_nlri.prefixes.iter().map(|p| p.prefix));
+        }
+
+        let mut withdrawn: Vec<IpNet> =
+            update.withdrawn_prefixes.iter().map(|p| p.prefix).collect();
+        if let Some(mp_unreach) = update.attributes.get_unreachable_nlri() {
+            withdrawn.extend(mp_unreach.prefixes.iter().map(|p| p.prefix));
+        }
+
+        announced.retain(|p| !is_default_route(p));
+        withdrawn.retain(|p| !is_default_route(p));
+        (announced, withdrawn)
+    }
 
-            if !as_set.is_empty() {
-                for asn in &as_set {
-                    let mut as_path = as_sequence.clone();
-                    as_path.push(*asn);
-
-                    path_data.insert_route(Route::new(
-                        as_path.clone(),
-                        fp.clone(),
-                        next_hop,
-                        id_peer_map[&rib_entry.peer_index],
-                        rib_entries.prefix.prefix,
-                        // communities.clone(),
-                        // large_communities.clone(),
-                    ));
+    /// Turn the announcements in a BGP4MP UPDATE message into routes, and record withdrawals
+    /// in the tracker (the routes are kept, see UpdateTracker).
+    /// BGP4MP records have no peer index table, the peer details come from the record itself.
+    /// No BGP ID is available for the peer so the unspecified address is used.
+    fn parse_bgp4mp_entry(
+        bgp4mp: &Bgp4MpEnum,
+        path_data: &mut PathData,
+        tracker: &mut UpdateTracker,
+        source: RouteSource,
+        count: &u32,
+    ) {
+        let fp = source.filename.get();
+        let msg = match bgp4mp {
+            Bgp4MpEnum::StateChange(state_change) => {
+                if state_change.old_state == BgpState::Established
+                    && state_change.new_state != BgpState::Established
+                {
+                    debug!(
+                        "Peer {} left Established state in file {} ({})",
+                        state_change.peer_ip, fp, count
+                    );
+                    tracker.reset_peer(state_change.peer_ip);
                 }
-            } else {
-                path_data.insert_route(Route::new(
-                    as_sequence.clone(),
-                    fp.clone(),
-                    next_hop,
-                    id_peer_map[&rib_entry.peer_index],
-                    rib_entries.prefix.prefix,
-                    // communities.clone(),
-                    // large_communities.clone(),
-                ));
+                return;
+            }
+            Bgp4MpEnum::Message(msg) => msg,
+        };
+
+        let BgpMessage::Update(update) = &msg.bgp_message else {
+            return;
+        };
+
+        let (announced, withdrawn) = get_update_prefixes(update);
+
+        for prefix in withdrawn {
+            tracker.withdraw(msg.peer_ip, prefix);
+        }
+
+        if announced.is_empty() {
+            return;
+        }
+
+        let peer = Peer::new(Ipv4Addr::UNSPECIFIED, msg.peer_ip, msg.peer_asn);
+        for prefix in announced {
+            if let Some(as_path) =
+                insert_routes(path_data, &update.attributes, peer, prefix, source, count)
+            {
+                tracker.announce(msg.peer_ip, prefix, &as_path);
             }
         }
     }
 
-    fn parse_rib_file(fp: String) -> PathData {
-        info!("Parsing {}", fp);
+    fn parse_rib_file(rib_file: &RibFile, batch_size: usize) -> PathData {
+        info!("Parsing {}", rib_file.filename);
+        parse_mrt_reader(
+            get_reader(&rib_file.filename),
+            RouteSource::from_rib_file(rib_file),
+            batch_size,
+        )
+    }
+
+    /// Parse a run of consecutive TABLE_DUMP/TABLE_DUMP_V2 records in parallel.
+    /// Each rayon worker builds a partial PathData which are then merged.
+    fn parse_table_dump_records(
+        records: &[(u32, MrtRecord)],
+        id_peer_map: &HashMap<u16, Peer>,
+        source: RouteSource,
+    ) -> PathData {
+        records
+            .par_iter()
+            .fold(PathData::new, |mut path_data, (count, mrt_entry)| {
+                match &mrt_entry.message {
+                    MrtMessage::TableDumpV2Message(_) => {
+                        parse_rib_entries(mrt_entry, &mut path_data, id_peer_map, source, count);
+                    }
+                    MrtMessage::TableDumpMessage(table_dump) => {
+                        parse_table_dump_entry(table_dump, &mut path_data, source, count);
+                    }
+                    MrtMessage::Bgp4Mp(_) => {
+                        panic!(
+                            "BGP4MP record {} in {} can't be parsed in parallel",
+                            count,
+                            source.filename.get()
+                        );
+                    }
+                }
+                path_data
+            })
+            .reduce(PathData::new, |mut path_data, mut other| {
+                path_data.move_from(&mut other);
+                path_data
+            })
+    }
 
+    /// Parse a batch of raw MRT records in parallel, records which fail to parse are skipped
+    fn parse_raw_records(batch: Vec<(u32, RawMrtRecord)>, fp: &String) -> Vec<(u32, MrtRecord)> {
+        batch
+            .into_par_iter()
+            .filter_map(|(count, raw_record)| match raw_record.parse() {
+                Ok(mrt_entry) => Some((count, mrt_entry)),
+                Err(e) => {
+                    warn!("Unable to parse record {} in {}: {}", count, fp, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Parse an MRT stream from any reader, every route is from the given source.
+    /// The stream is read in batches of raw records which are decoded in parallel. Runs of
+    /// RIB records within a batch are then also parsed in parallel, after the peer index
+    /// table has been read. BGP4MP records are parsed in order, as the withdrawal
+    /// tracking depends on the order of updates.
+    fn parse_mrt_reader<R: Read>(reader: R, source: RouteSource, batch_size: usize) -> PathData {
+        let fp = source.filename.get();
         let mut path_data = PathData::new();
         let mut count: u32 = 0;
         let mut id_peer_map = HashMap::<u16, Peer>::new();
-
-        let parser =
-            BgpkitParser::new(fp.as_str()).unwrap_or_else(|_| panic!("Unable to parse {}", fp));
-
-        for mrt_entry in parser.into_record_iter() {
-            if count == 0 {
-                id_peer_map = get_peer_id_map(&fp);
-                debug!("Peer Map: {:#?}\n", id_peer_map);
-                count += 1;
-                continue;
+        let mut tracker = UpdateTracker::default();
+
+        let mut raw_records = BgpkitParser::from_reader(reader).into_raw_record_iter();
+
+        loop {
+            let batch: Vec<(u32, RawMrtRecord)> = raw_records
+                .by_ref()
+                .take(batch_size.max(1))
+                .map(|raw_record| {
+                    count += 1;
+                    (count - 1, raw_record)
+                })
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
+            debug!("Parsing batch of {} records from {}", batch.len(), fp);
+
+            let records = parse_raw_records(batch, fp);
+            let mut run_start = 0;
+
+            for (i, (record_count, mrt_entry)) in records.iter().enumerate() {
+                match &mrt_entry.message {
+                    MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(_)) => {
+                        path_data.move_from(&mut parse_table_dump_records(
+                            &records[run_start..i],
+                            &id_peer_map,
+                            source,
+                        ));
+                        run_start = i + 1;
+                        id_peer_map = get_peer_id_map(mrt_entry, fp);
+                        debug!("Peer Map: {:#?}\n", id_peer_map);
+                    }
+                    MrtMessage::Bgp4Mp(bgp4mp) => {
+                        path_data.move_from(&mut parse_table_dump_records(
+                            &records[run_start..i],
+                            &id_peer_map,
+                            source,
+                        ));
+                        run_start = i + 1;
+                        parse_bgp4mp_entry(
+                            bgp4mp,
+                            &mut path_data,
+                            &mut tracker,
+                            source,
+                            record_count,
+                        );
+                    }
+                    MrtMessage::TableDumpV2Message(_) | MrtMessage::TableDumpMessage(_) => {}
+                }
             }
 
-            parse_rib_entries(&mrt_entry, &mut path_data, &id_peer_map, &fp, &count);
+            path_data.move_from(&mut parse_table_dump_records(
+                &records[run_start..],
+                &id_peer_map,
+                source,
+            ));
+        }
 
-            count += 1;
+        if tracker.announcements > 0 || tracker.withdrawals > 0 || tracker.unmatched_withdrawals > 0
+        {
+            info!(
+                "Update stats for {}: {} announcements, {} withdrawals, {} implicit withdrawals, \
+                {} unmatched withdrawals, {} session resets, {} routes still active",
+                fp,
+                tracker.announcements,
+                tracker.withdrawals,
+                tracker.implicit_withdrawals,
+                tracker.unmatched_withdrawals,
+                tracker.session_resets,
+                tracker.active.len(),
+            );
         }
 
         info!(
@@ -312,4 +637,346 @@ pub mod rib_parser {
 
         path_data
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use bgpkit_parser::encoder::{MrtRibEncoder, MrtUpdatesEncoder};
+        use bgpkit_parser::models::{
+            AsPath as BgpAsPath, AsPathSegment, BgpElem, CommonHeader, ElemType, EntryType,
+            NetworkPrefix, Nlri, RibEntry,
+        };
+        use std::io::Cursor;
+
+        /// A TABLE_DUMP_V2 RIB with 3 peers, each seeing 200 prefixes from 20 origins.
+        /// The encoder always uses MP_REACH_NLRI, and writes AS4_PATH instead of
+        /// AS_PATH unless the origin is a 2-byte ASN (see test_as4_path_without_as_path()).
+        fn get_rib_bytes() -> Vec<u8> {
+            let mut encoder = MrtRibEncoder::new();
+            for peer in 1..=3_u32 {
+                for i in 0..200_u32 {
+                    let elem = BgpElem {
+                        peer_ip: format!("2001:db8::{}", peer).parse().unwrap(),
+                        peer_asn: Asn::new_32bit(peer),
+                        prefix: NetworkPrefix::new(
+                            format!("2001:db8:{:x}::/48", i).parse().unwrap(),
+                            None,
+                        ),
+                        next_hop: Some(format!("2001:db8::{}", peer).parse().unwrap()),
+                        as_path: Some(BgpAsPath::from_segments(Vec::from([
+                            AsPathSegment::AsSequence(Vec::from(
+                                [peer, 100 + i % 7, 1000 + i % 20]
+                                    .map(|a| Asn::new_16bit(a as u16)),
+                            )),
+                        ]))),
+                        ..Default::default()
+                    };
+                    encoder.process_elem(&elem);
+                }
+            }
+            encoder.export_bytes().to_vec()
+        }
+
+        #[test]
+        fn test_batched_parse_matches_sequential() {
+            let bytes = get_rib_bytes();
+            let source = RouteSource::from_filename("rib.test");
+            let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+            let sequential = parse_mrt_reader(Cursor::new(bytes.clone()), source, usize::MAX);
+            let batched = pool.install(|| parse_mrt_reader(Cursor::new(bytes), source, 7));
+
+            assert_eq!(sequential.get_origins_count(), 20);
+            assert_eq!(sequential.get_as_paths_count(), 3 * 140);
+            assert_eq!(batched.get_origins_count(), sequential.get_origins_count());
+            assert_eq!(
+                batched.get_as_paths_count(),
+                sequential.get_as_paths_count()
+            );
+        }
+
+        #[test]
+        fn test_merge_parsed() {
+            // The same RIB from 4 collectors with the same dump filename, the routes are
+            // kept per collector, and one more copy of a collector's RIB adds nothing
+            let rib_files: Vec<RibFile> = [
+                "route-views2",
+                "route-views.amsix",
+                "route-views.sg",
+                "route-views6",
+                "route-views.amsix",
+            ]
+            .into_iter()
+            .map(|collector_id| RibFile {
+                collector_id: String::from(collector_id),
+                ..RibFile::from_filename("rib.20250922.0000.bz2")
+            })
+            .collect();
+            let bytes = get_rib_bytes();
+            let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+
+            let single = parse_mrt_reader(
+                Cursor::new(bytes.clone()),
+                RouteSource::from_rib_file(&rib_files[0]),
+                100,
+            );
+            let path_data = pool.install(|| {
+                merge_parsed(&rib_files, |rib_file| {
+                    parse_mrt_reader(
+                        Cursor::new(bytes.clone()),
+                        RouteSource::from_rib_file(rib_file),
+                        100,
+                    )
+                })
+            });
+            assert_eq!(path_data.get_origins_count(), 20);
+            assert_eq!(path_data.get_as_paths_count(), 3 * 140);
+            assert_eq!(path_data.get_routes_count(), 4 * single.get_routes_count());
+        }
+
+        #[test]
+        fn test_as4_path_without_as_path() {
+            let mut encoder = MrtRibEncoder::new();
+            encoder.process_elem(&BgpElem {
+                peer_ip: "2001:db8::1".parse().unwrap(),
+                peer_asn: Asn::new_32bit(1),
+                prefix: NetworkPrefix::new("2001:db8::/48".parse().unwrap(), None),
+                next_hop: Some("2001:db8::1".parse().unwrap()),
+                as_path: Some(BgpAsPath::from_sequence([1, 2, 196608])),
+                ..Default::default()
+            });
+            let bytes = encoder.export_bytes().to_vec();
+
+            let path_data = parse_mrt_reader(
+                Cursor::new(bytes),
+                RouteSource::from_filename("rib.test"),
+                1,
+            );
+            let route = path_data.get_routes().next().unwrap();
+            assert_eq!(route.get_origin(), &Asn::new_32bit(196608));
+            assert_eq!(
+                route.get_as_path(),
+                &Vec::from([1, 2, 196608].map(Asn::new_32bit))
+            );
+        }
+
+        fn get_update_elem(elem_type: ElemType, prefix: &str, as_path: &[u32]) -> BgpElem {
+            BgpElem {
+                elem_type,
+                peer_ip: "2001:db8::1".parse().unwrap(),
+                peer_asn: Asn::new_32bit(1),
+                prefix: NetworkPrefix::new(prefix.parse().unwrap(), None),
+                next_hop: Some("2001:db8::1".parse().unwrap()),
+                as_path: Some(BgpAsPath::from_sequence(as_path)),
+                ..Default::default()
+            }
+        }
+
+        /// A BGP4MP update file with an implicit withdrawal, an explicit withdrawal,
+        /// and a withdrawal of a prefix which was never announced
+        fn get_updates_bytes() -> Vec<u8> {
+            let mut encoder = MrtUpdatesEncoder::new();
+            for (elem_type, prefix, as_path) in [
+                (ElemType::ANNOUNCE, "2001:db8:1::/48", [1, 2, 3].as_slice()),
+                (ElemType::ANNOUNCE, "2001:db8:2::/48", &[1, 4, 5]),
+                (ElemType::ANNOUNCE, "2001:db8:1::/48", &[1, 6, 3]),
+                (ElemType::WITHDRAW, "2001:db8:2::/48", &[]),
+                (ElemType::WITHDRAW, "2001:db8:3::/48", &[]),
+            ] {
+                encoder.process_elem(&get_update_elem(elem_type, prefix, as_path));
+            }
+            encoder.export_bytes().to_vec()
+        }
+
+        #[test]
+        fn test_parse_bgp4mp_updates() {
+            let bytes = get_updates_bytes();
+            let source = RouteSource::from_filename("updates.test");
+
+            // Withdrawn routes are kept, PathData holds every AS path seen
+            let path_data = parse_mrt_reader(Cursor::new(bytes.clone()), source, 2);
+            assert_eq!(path_data.get_routes().count(), 3);
+            assert_eq!(path_data.get_origins_count(), 2);
+            assert_eq!(path_data.get_as_paths_count(), 3);
+            for route in path_data.get_routes() {
+                assert_eq!(route.get_peer().peer_asn, Asn::new_32bit(1));
+                assert_eq!(
+                    route.get_peer().peer_ip,
+                    "2001:db8::1".parse::<IpAddr>().unwrap()
+                );
+            }
+
+            let mut path_data = PathData::new();
+            let mut tracker = UpdateTracker::default();
+            for (count, record) in BgpkitParser::from_reader(Cursor::new(bytes))
+                .into_record_iter()
+                .enumerate()
+            {
+                let MrtMessage::Bgp4Mp(bgp4mp) = &record.message else {
+                    panic!("Expected a BGP4MP record: {:#?}", record);
+                };
+                parse_bgp4mp_entry(
+                    bgp4mp,
+                    &mut path_data,
+                    &mut tracker,
+                    source,
+                    &(count as u32),
+                );
+            }
+            assert_eq!(tracker.announcements, 3);
+            assert_eq!(tracker.implicit_withdrawals, 1);
+            assert_eq!(tracker.withdrawals, 1);
+            assert_eq!(tracker.unmatched_withdrawals, 1);
+            assert_eq!(tracker.active.len(), 1);
+        }
+
+        #[test]
+        fn test_parse_bgp4mp_ipv4_updates() {
+            // IPv4 prefixes are encoded in MP_REACH_NLRI, with an IPv4 next-hop
+            let mut encoder = MrtUpdatesEncoder::new();
+            for (prefix, as_path) in [
+                ("198.51.100.0/24", [1, 2, 3].as_slice()),
+                ("2001:db8:1::/48", &[1, 2, 3]),
+            ] {
+                let mut elem = get_update_elem(ElemType::ANNOUNCE, prefix, as_path);
+                if prefix.contains('.') {
+                    elem.next_hop = Some("192.0.2.1".parse().unwrap());
+                }
+                encoder.process_elem(&elem);
+            }
+            let bytes = encoder.export_bytes().to_vec();
+
+            let source = RouteSource::from_filename("updates.test");
+            let path_data = parse_mrt_reader(Cursor::new(bytes), source, 2);
+            let mut next_hops: Vec<(String, String)> = path_data
+                .get_routes()
+                .map(|route| {
+                    (
+                        route.get_prefix().to_string(),
+                        route.get_next_hop().to_string(),
+                    )
+                })
+                .collect();
+            next_hops.sort();
+            assert_eq!(
+                next_hops,
+                Vec::from([
+                    ("198.51.100.0/24".to_string(), "192.0.2.1".to_string()),
+                    ("2001:db8:1::/48".to_string(), "2001:db8::1".to_string()),
+                ])
+            );
+        }
+
+        #[test]
+        fn test_get_next_hop() {
+            let fp = String::from("updates.test");
+            let mp_reach = |next_hop: Option<&str>| {
+                AttributeValue::MpReachNlri(Nlri::new_reachable(
+                    NetworkPrefix::new("198.51.100.0/24".parse().unwrap(), None),
+                    next_hop.map(|next_hop| next_hop.parse().unwrap()),
+                ))
+            };
+            let next_hop = |values: Vec<AttributeValue>| {
+                get_next_hop(&Attributes::from_iter(values), &fp, &0).map(|ip| ip.to_string())
+            };
+
+            assert_eq!(
+                next_hop(Vec::from([mp_reach(Some("192.0.2.1"))])),
+                Some("192.0.2.1".to_string())
+            );
+            assert_eq!(
+                next_hop(Vec::from([mp_reach(Some("2001:db8::1"))])),
+                Some("2001:db8::1".to_string())
+            );
+            assert_eq!(
+                next_hop(Vec::from([
+                    mp_reach(None),
+                    AttributeValue::NextHop("192.0.2.2".parse().unwrap()),
+                ])),
+                Some("192.0.2.2".to_string())
+            );
+            assert_eq!(next_hop(Vec::from([mp_reach(None)])), None);
+            assert_eq!(next_hop(Vec::new()), None);
+        }
+
+        fn get_table_dump_attributes() -> Attributes {
+            Attributes::from_iter(Vec::from([
+                AttributeValue::AsPath {
+                    path: BgpAsPath::from_sequence([65001, 65002, 65003]),
+                    is_as4: false,
+                },
+                AttributeValue::NextHop("192.0.2.1".parse().unwrap()),
+                AttributeValue::Communities(Vec::from([Community::Custom(
+                    Asn::new_16bit(65002),
+                    100,
+                )])),
+            ]))
+        }
+
+        #[test]
+        fn test_table_dump_v1_matches_v2() {
+            let source = RouteSource::from_filename("rib.test");
+            let prefix = NetworkPrefix::new("198.51.100.0/24".parse().unwrap(), None);
+            let peer_ip: IpAddr = "192.0.2.1".parse().unwrap();
+            let peer_asn = Asn::new_16bit(65001);
+
+            let table_dump = MrtMessage::TableDumpMessage(TableDumpMessage {
+                view_number: 0,
+                sequence_number: 0,
+                prefix,
+                status: 1,
+                originated_time: 0,
+                peer_ip,
+                peer_asn,
+                attributes: get_table_dump_attributes(),
+            });
+            let v1_bytes = MrtRecord {
+                common_header: CommonHeader {
+                    timestamp: 0,
+                    microsecond_timestamp: None,
+                    entry_type: EntryType::TABLE_DUMP,
+                    entry_subtype: 1,
+                    // Set to the message length by encode()
+                    length: u32::MAX,
+                },
+                message: table_dump,
+            }
+            .encode();
+            let v1 = parse_mrt_reader(Cursor::new(v1_bytes), source, 1);
+
+            // The v1 peer has no BGP ID, so the V2 peer index table is built to match
+            let id_peer_map =
+                HashMap::from([(0, Peer::new(Ipv4Addr::UNSPECIFIED, peer_ip, peer_asn))]);
+            let rib_afi = MrtRecord {
+                common_header: CommonHeader {
+                    timestamp: 0,
+                    microsecond_timestamp: None,
+                    entry_type: EntryType::TABLE_DUMP_V2,
+                    entry_subtype: TableDumpV2Type::RibIpv4Unicast as u16,
+                    length: 0,
+                },
+                message: MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(
+                    RibAfiEntries {
+                        rib_type: TableDumpV2Type::RibIpv4Unicast,
+                        sequence_number: 0,
+                        prefix,
+                        rib_entries: Vec::from([RibEntry {
+                            peer_index: 0,
+                            originated_time: 0,
+                            path_id: None,
+                            attributes: get_table_dump_attributes(),
+                        }]),
+                    },
+                )),
+            };
+            let v2 = parse_table_dump_records(&[(0, rib_afi)], &id_peer_map, source);
+
+            let v1_routes: Vec<&Route> = v1.get_routes().collect();
+            let v2_routes: Vec<&Route> = v2.get_routes().collect();
+            assert_eq!(v1_routes.len(), 1);
+            assert_eq!(v1_routes, v2_routes);
+            assert_eq!(v1_routes[0].get_prefix(), &prefix.prefix);
+            assert_eq!(v1_routes[0].get_communities().len(), 1);
+        }
+    }
 }
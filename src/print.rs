@@ -1,9 +1,15 @@
+use crate::reader::mrt_reader::get_reader;
 use bgpkit_parser::BgpkitParser;
+use std::io::Read;
 
-/// Print a specific record/entry from an MRT file
-pub fn print_entry(index: &u32, filename: &String) {
-    let parser = BgpkitParser::new(filename.as_str())
-        .unwrap_or_else(|_| panic!("Unable to parse {}", filename));
+/// Print a specific record/entry from an MRT file, URL, or stdin ("-")
+pub fn print_entry(index: &u32, filename: &str) {
+    print_reader_entry(index, get_reader(filename));
+}
+
+/// Print a specific record/entry from an MRT stream
+pub fn print_reader_entry<R: Read>(index: &u32, reader: R) {
+    let parser = BgpkitParser::from_reader(reader);
     let mut count: u32 = 0;
 
     for mrt_entry in parser.into_record_iter() {
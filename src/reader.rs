@@ -0,0 +1,108 @@
+pub mod mrt_reader {
+    use bzip2::read::MultiBzDecoder;
+    use flate2::read::MultiGzDecoder;
+    use log::{debug, info};
+    use reqwest::blocking::Client;
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader, Read};
+    use std::time::Duration;
+
+    /// Read MRT data from stdin
+    pub const STDIN: &str = "-";
+
+    #[derive(Debug, PartialEq)]
+    enum Compression {
+        Bzip2,
+        Gzip,
+        None,
+    }
+
+    /// Detect the compression type from the magic bytes at the start of the stream
+    fn get_compression(magic: &[u8]) -> Compression {
+        if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Wrap a raw reader with a decompressor, based on the first bytes of the stream
+    pub fn get_decompressed_reader<R: Read + Send + 'static>(reader: R) -> Box<dyn Read + Send> {
+        let mut reader = BufReader::new(reader);
+        let magic = reader
+            .fill_buf()
+            .unwrap_or_else(|e| panic!("Unable to read MRT stream: {}", e));
+
+        let compression = get_compression(magic);
+        debug!("Detected compression {:?}", compression);
+        match compression {
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::None => Box::new(reader),
+        }
+    }
+
+    pub fn is_url(source: &str) -> bool {
+        source.starts_with("http://") || source.starts_with("https://")
+    }
+
+    /// Return a reader for an MRT source, which is either stdin ("-"), an
+    /// http(s) URL which is streamed (not written to disk), or a local file.
+    /// Compression is detected from the data, not the filename.
+    pub fn get_reader(source: &str) -> Box<dyn Read + Send> {
+        if source == STDIN {
+            info!("Reading MRT data from stdin");
+            get_decompressed_reader(io::stdin())
+        } else if is_url(source) {
+            info!("Streaming MRT data from {}", source);
+            let response = Client::builder()
+                .timeout(Duration::from_secs(300))
+                .build()
+                .unwrap()
+                .get(source)
+                .send()
+                .and_then(|r| r.error_for_status())
+                .unwrap_or_else(|e| panic!("HTTP GET failed for {}: {}", source, e));
+            get_decompressed_reader(response)
+        } else {
+            let file =
+                File::open(source).unwrap_or_else(|e| panic!("Unable to open {}: {}", source, e));
+            get_decompressed_reader(file)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use flate2::Compression as GzLevel;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        #[test]
+        fn test_get_compression() {
+            assert_eq!(get_compression(b"BZh91AY"), Compression::Bzip2);
+            assert_eq!(get_compression(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+            assert_eq!(get_compression(&[0x68, 0xd0, 0x4c]), Compression::None);
+            assert_eq!(get_compression(&[]), Compression::None);
+        }
+
+        #[test]
+        fn test_get_decompressed_reader() {
+            let data = b"MRT record bytes".to_vec();
+
+            let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+            encoder.write_all(&data).unwrap();
+            let gzipped = encoder.finish().unwrap();
+
+            for input in [data.clone(), gzipped] {
+                let mut output = Vec::new();
+                get_decompressed_reader(io::Cursor::new(input))
+                    .read_to_end(&mut output)
+                    .unwrap();
+                assert_eq!(output, data);
+            }
+        }
+    }
+}
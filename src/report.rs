@@ -0,0 +1,230 @@
+pub mod path_report {
+    use crate::mrt_as_path::as_path::AsPath;
+    use crate::mrt_divergent_paths::divergent_paths::DivergentPaths;
+    use crate::search_aggregates::aggregate_search::HiddenOrigins;
+    use crate::search_aggregators::aggregator_search::AggregatorRoutes;
+    use crate::search_communities::community_search::CommunityWithoutAsn;
+    use crate::search_peers::peer_search::{FirstHopCounts, FirstHopReport};
+    use crate::search_special_asns::special_asn_search::SpecialAsnGroup;
+    use crate::search_valley_free::valley_free_search::{DivergenceLinks, ValleyFreeViolation};
+    use bgpkit_parser::models::Asn;
+
+    fn format_neighbour(asn: &Option<Asn>) -> String {
+        asn.map_or(String::from("(path end)"), |asn| format!("AS{}", asn))
+    }
+
+    fn format_first_hop_counts(counts: &FirstHopCounts) -> String {
+        format!(
+            "{} routes, first hop {}, peer absent {}, peer elsewhere {}",
+            counts.get_total(),
+            counts.peer,
+            counts.peer_absent,
+            counts.peer_elsewhere
+        )
+    }
+
+    /// Print each pair of divergent paths with the routes, peers and files which support them
+    pub fn print_divergent_paths(divergent_paths: &[DivergentPaths]) {
+        for found in divergent_paths {
+            println!(
+                "Origin AS{} paths diverge after AS{}: {:?} vs {:?}",
+                found.origin, found.shared_asn, found.sub_path_a, found.sub_path_b,
+            );
+            println!(
+                "    AS path A {:?} prepends {:?}",
+                found.as_path_a.get_as_path(),
+                found.prepend_counts_a
+            );
+            println!(
+                "    AS path B {:?} prepends {:?}",
+                found.as_path_b.get_as_path(),
+                found.prepend_counts_b
+            );
+            for peer in &found.peers {
+                println!("    Peer AS{} {}", peer.peer_asn, peer.peer_ip);
+            }
+            for filename in &found.filenames {
+                println!("    File {}", filename);
+            }
+            for route in &found.routes {
+                println!(
+                    "    Route {} via {} {:?} prepends {:?}",
+                    route.get_prefix(),
+                    route.get_next_hop(),
+                    route.get_as_path(),
+                    route.get_prepend_counts()
+                );
+            }
+        }
+    }
+
+    /// Print AS paths where AS_TRANS hides a 4-byte ASN, with the raw AS_PATH
+    /// and AS4_PATH of each route that used the path.
+    pub fn print_as_trans_paths(as_trans_paths: &[&AsPath]) {
+        for as_path in as_trans_paths {
+            println!("AS_TRANS in AS path {:?}", as_path.get_as_path());
+            for route in as_path.get_routes() {
+                let (raw_as_path, raw_as4_path) = route
+                    .get_raw_as_paths()
+                    .map_or((String::from("-"), String::from("-")), |raw| {
+                        (raw.as_path.to_string(), raw.as4_path.to_string())
+                    });
+                println!(
+                    "    Route {} from peer AS{} {} AS_PATH [{}] AS4_PATH [{}]",
+                    route.get_prefix(),
+                    route.get_peer().peer_asn,
+                    route.get_peer().peer_ip,
+                    raw_as_path,
+                    raw_as4_path
+                );
+            }
+        }
+    }
+
+    /// Print routes with a community from an ASN which isn't in the AS path
+    pub fn print_communities_without_asn(communities: &[CommunityWithoutAsn]) {
+        for found in communities {
+            println!(
+                "Missing AS{} ({:?} community) in AS path {:?} for {} from peer AS{} {} in {}",
+                found.missing_asn,
+                found.community_type,
+                found.route.get_as_path(),
+                found.route.get_prefix(),
+                found.peer.peer_asn,
+                found.peer.peer_ip,
+                found.filename,
+            );
+        }
+    }
+
+    /// Print where the peer ASN appears in the AS paths, per peer and per collector,
+    /// followed by the peers which systematically hide themselves
+    pub fn print_peer_first_hops(report: &FirstHopReport) {
+        for found in &report.peers {
+            println!(
+                "Peer AS{} {} on {:?}: {}",
+                found.peer.peer_asn,
+                found.peer.peer_ip,
+                found.collectors,
+                format_first_hop_counts(&found.counts)
+            );
+        }
+        for (collector, counts) in &report.collectors {
+            println!(
+                "Collector {}: {}",
+                collector,
+                format_first_hop_counts(counts)
+            );
+        }
+        for found in report.get_hiding_peers() {
+            println!(
+                "Peer AS{} {} hides itself from {} of {} routes on {:?}",
+                found.peer.peer_asn,
+                found.peer.peer_ip,
+                found.counts.peer_absent,
+                found.counts.get_total(),
+                found.collectors
+            );
+        }
+    }
+
+    /// Print private, documentation and reserved ASNs grouped by their public neighbours,
+    /// with the transit ASNs which let them through
+    pub fn print_special_asns(groups: &[SpecialAsnGroup]) {
+        for found in groups {
+            println!(
+                "{:?} between {} and {} in {} AS paths ({} routes), let through by {:?}",
+                found.special_asns,
+                format_neighbour(&found.left),
+                format_neighbour(&found.right),
+                found.as_paths.len(),
+                found.get_routes_count(),
+                found.transit_asns
+            );
+            for (as_path, routes) in &found.as_paths {
+                println!("    AS path {:?} ({} routes)", as_path.get(), routes.len());
+            }
+        }
+    }
+
+    /// Print aggregates whose AS set hides the origins, with the more-specifics
+    /// of the aggregate and their origins
+    pub fn print_hidden_origins(hidden_origins: &[HiddenOrigins]) {
+        for found in hidden_origins {
+            println!(
+                "Aggregate {} AS path {:?} AS set {:?}, hidden origins {:?}, not seen as origins {:?}",
+                found.route.get_prefix(),
+                found.route.get_as_path(),
+                found.as_set_asns,
+                found.confirmed_origins,
+                found.unconfirmed_origins
+            );
+            println!(
+                "    Peer AS{} {} in {}",
+                found.route.get_peer().peer_asn,
+                found.route.get_peer().peer_ip,
+                found.route.get_filename()
+            );
+            for (prefix, origins) in &found.more_specifics {
+                println!("    More-specific {} from {:?}", prefix, origins);
+            }
+        }
+    }
+
+    /// Print routes whose aggregator isn't in the AS path or isn't the origin, per aggregator
+    pub fn print_aggregator_mismatches(aggregators: &[AggregatorRoutes]) {
+        for found in aggregators {
+            println!(
+                "Aggregator AS{} {:?}: absent from {} AS paths, not the origin of {}",
+                found.aggregator_asn,
+                found.aggregator_ips,
+                found.absent_from_path.len(),
+                found.not_origin.len()
+            );
+            for route in &found.absent_from_path {
+                println!(
+                    "    Absent {} AS path {:?}",
+                    route.get_prefix(),
+                    route.get_as_path()
+                );
+            }
+            for route in &found.not_origin {
+                println!(
+                    "    Not origin {} AS path {:?}",
+                    route.get_prefix(),
+                    route.get_as_path()
+                );
+            }
+        }
+    }
+
+    /// Print AS paths which aren't valley-free, with the first link which breaks the rule
+    pub fn print_valley_free_violations(violations: &[ValleyFreeViolation]) {
+        for found in violations {
+            println!(
+                "AS path {:?} isn't valley-free at AS{} -> AS{} ({:?})",
+                found.as_path.get_as_path(),
+                found.link.0,
+                found.link.1,
+                found.relationship
+            );
+        }
+    }
+
+    /// Print each divergence with the links in its sub-paths which have no known
+    /// relationship, which may be a hidden link
+    pub fn print_divergence_links(divergence_links: &[DivergenceLinks]) {
+        for found in divergence_links {
+            let divergent = found.divergent_paths;
+            println!(
+                "Origin AS{} paths diverge after AS{}: {:?} vs {:?}, unknown links {:?} vs {:?}",
+                divergent.origin,
+                divergent.shared_asn,
+                divergent.sub_path_a,
+                divergent.sub_path_b,
+                found.unknown_links_a,
+                found.unknown_links_b
+            );
+        }
+    }
+}
@@ -1,68 +1,433 @@
 pub mod rib_getter {
-    use crate::http::http_client::download_file;
+    use crate::http::http_client::{Download, DownloadConfig, download_files};
     use bgpkit_broker::BgpkitBroker;
-    use log::{debug, info};
+    use chrono::{NaiveDateTime, Timelike};
+    use log::{debug, info, warn};
+    use std::collections::BTreeMap;
     use std::fs;
     use std::path::Path;
 
+    /// The first part of the MRT dump filenames published by RIS and RouteViews
+    const DUMP_TYPES: [&str; 4] = ["bview", "latest-bview", "rib", "updates"];
+
     #[derive(Debug)]
     pub struct RibFile {
         pub url: String,
         pub filename: String,
+        /// The collector ID from the broker or mirror, e.g. rrc00 or route-views.amsix
+        pub collector_id: String,
+        /// Start time of the RIB dump, "yyyy-mm-dd hh:mm", used to group RIBs by snapshot.
+        /// Routes keep their snapshot, so the same route in two snapshots is two routes.
+        pub snapshot: String,
+        /// Exact size in bytes, if known, used to verify downloads
+        pub size: Option<u64>,
+    }
+
+    /// Select RIBs by collector ID/glob and project
+    #[derive(Debug, Default)]
+    pub struct RibFilter {
+        pub include: Vec<String>,
+        pub exclude: Vec<String>,
+        pub project: Option<String>,
+    }
+
+    impl RibFilter {
+        /// A collector matches if it's in the project (when set), matches any
+        /// include pattern (when set), and doesn't match any exclude pattern
+        pub fn matches(&self, collector_id: &str) -> bool {
+            if let Some(project) = &self.project
+                && get_project(collector_id) != project
+            {
+                return false;
+            }
+            if !self.include.is_empty() && !self.include.iter().any(|p| glob_match(p, collector_id))
+            {
+                return false;
+            }
+            !self.exclude.iter().any(|p| glob_match(p, collector_id))
+        }
     }
 
-    /// Download all the ribs files for a specific day
-    pub fn download_ribs_for_day(date: &str, dir: &str) -> Vec<RibFile> {
-        info!("Downloading MRT RIBs for {} to {}", date, dir);
-        let rib_files = get_rib_list_for_day(date, dir);
-        download_ribs_to_dir(dir, &rib_files);
+    /// Match text against a glob pattern which supports * and ?
+    fn glob_match(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        let (mut p, mut t) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while t < text.len() {
+            if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+                p += 1;
+                t += 1;
+            } else if p < pattern.len() && pattern[p] == '*' {
+                backtrack = Some((p, t));
+                p += 1;
+            } else if let Some((star_p, star_t)) = backtrack {
+                p = star_p + 1;
+                t = star_t + 1;
+                backtrack = Some((star_p, star_t + 1));
+            } else {
+                return false;
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+
+    /// Return the project a collector belongs to, "ris" or "route-views"
+    pub fn get_project(collector_id: &str) -> &'static str {
+        if collector_id.starts_with("rrc") {
+            "ris"
+        } else {
+            "route-views"
+        }
+    }
+
+    /// Return the collector ID for a file with no known collector (e.g. passed on the
+    /// command line). Local RIB filenames are prefixed with the collector ID (see
+    /// get_rib_filename()), otherwise the collector is taken from the directories of
+    /// the standard archive layouts (see mirror::get_month_dir()). If neither finds it,
+    /// the whole filename is used, so routes from unknown files are never merged.
+    pub fn get_collector_from_filename(filename: &str) -> &str {
+        let path = Path::new(filename);
+        let basename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(filename);
+
+        let dump_type_pos = basename
+            .split('.')
+            .position(|part| DUMP_TYPES.contains(&part));
+        if let Some(pos) = dump_type_pos.filter(|pos| *pos > 0) {
+            let len: usize = basename
+                .split('.')
+                .take(pos)
+                .map(|part| part.len() + 1)
+                .sum();
+            let collector_id = &basename[..len - 1];
+            return collector_id.strip_prefix("ris.").unwrap_or(collector_id);
+        }
+
+        let dirs: Vec<&str> = path
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.iter())
+            .filter_map(|dir| dir.to_str())
+            .collect();
+        if let Some(collector_id) = dirs
+            .iter()
+            .rev()
+            .find(|dir| dir.starts_with("rrc") || dir.starts_with("route-views"))
+        {
+            collector_id
+        } else if dump_type_pos.is_some() && dirs.contains(&"bgpdata") {
+            "route-views2"
+        } else {
+            filename
+        }
+    }
+
+    /// Return the start time of a RIB dump from its filename,
+    /// e.g. rib.20250922.0000.bz2, bview.20250922.0000.gz or ris.rrc00.bview.20250922.0000.gz
+    pub fn get_rib_timestamp(basename: &str) -> Option<NaiveDateTime> {
+        let parts: Vec<&str> = basename.split('.').collect();
+        let pos = parts
+            .iter()
+            .position(|part| *part == "rib" || *part == "bview")?;
+        let (day, time) = (parts.get(pos + 1)?, parts.get(pos + 2)?);
+        NaiveDateTime::parse_from_str(&format!("{}{}", day, time), "%Y%m%d%H%M").ok()
+    }
+
+    /// Whether a RIB dump which started at timestamp is one of the snapshot hours,
+    /// dumps which don't start on the hour (e.g. 08:15) are never selected
+    pub fn is_snapshot_hour(timestamp: &NaiveDateTime, hours: &[u32]) -> bool {
+        hours.contains(&timestamp.hour()) && timestamp.minute() == 0
+    }
+
+    /// The snapshot of a RIB dump which started at timestamp
+    pub fn get_snapshot(timestamp: &NaiveDateTime) -> String {
+        timestamp.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    /// Return the snapshot of a file with no known snapshot from its dump name,
+    /// files without a timestamp in their name (e.g. latest-bview.gz) have none
+    pub fn get_snapshot_from_filename(filename: &str) -> String {
+        let basename = Path::new(filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(filename);
+        get_rib_timestamp(basename)
+            .map(|timestamp| get_snapshot(&timestamp))
+            .unwrap_or_default()
+    }
+
+    impl RibFile {
+        /// A RIB file which already exists locally, the collector and snapshot
+        /// are taken from the filename
+        pub fn from_filename(filename: &str) -> Self {
+            RibFile {
+                url: String::new(),
+                filename: filename.to_string(),
+                collector_id: get_collector_from_filename(filename).to_string(),
+                snapshot: get_snapshot_from_filename(filename),
+                size: None,
+            }
+        }
+    }
+
+    /// Download the RIB files found for a time range
+    pub fn download_ribs(rib_files: Vec<RibFile>, config: &DownloadConfig) -> Vec<RibFile> {
+        for (snapshot, snapshot_files) in group_by_snapshot(&rib_files) {
+            info!("Snapshot {}: {} RIB files", snapshot, snapshot_files.len());
+        }
+        download_ribs_to_dir(rib_files, config)
+    }
+
+    /// Print the RIBs which would be parsed, without downloading them
+    pub fn print_rib_list(rib_files: &[RibFile]) {
+        let mut total: u64 = 0;
+        for rib_file in rib_files {
+            let size = rib_file.size.unwrap_or(0);
+            total += size;
+            println!("{}\t{}\t{}", rib_file.url, size, rib_file.filename);
+        }
+        println!("{} RIB files, {} bytes", rib_files.len(), total);
+    }
+
+    /// Download the RIB files, returning only those which were downloaded successfully
+    fn download_ribs_to_dir(rib_files: Vec<RibFile>, config: &DownloadConfig) -> Vec<RibFile> {
+        let mut downloads = Vec::<Download>::new();
+        for rib_file in &rib_files {
+            let mrt_path = Path::new(&rib_file.filename).parent().unwrap();
+            if !mrt_path.exists() {
+                debug!("Creating path: {}", mrt_path.to_str().unwrap());
+                fs::create_dir_all(mrt_path).unwrap();
+            }
+
+            downloads.push(Download {
+                url: rib_file.url.clone(),
+                dest: Path::new(&rib_file.filename).to_path_buf(),
+                size: rib_file.size,
+            });
+        }
+
+        let failed = download_files(&downloads, config);
+        if failed.is_empty() {
+            return rib_files;
+        }
+
+        warn!(
+            "Skipping {} RIB files which failed to download",
+            failed.len()
+        );
         rib_files
+            .into_iter()
+            .filter(|rib_file| !failed.iter().any(|f| f.url == rib_file.url))
+            .collect()
     }
 
-    fn download_ribs_to_dir(dir: &str, rib_files: &Vec<RibFile>) {
-        let mrt_path = Path::new(dir);
-        if !mrt_path.exists() {
-            debug!("Creating path: {}", mrt_path.to_str().unwrap());
-            fs::create_dir(mrt_path).unwrap();
+    /// Return the local filename for a RIB, within a per-day sub-directory of dir
+    pub fn get_rib_filename(
+        dir: &str,
+        collector_id: &str,
+        timestamp: &NaiveDateTime,
+        basename: &str,
+    ) -> String {
+        let source = get_project(collector_id);
+
+        let day = timestamp.format("%Y-%m-%d");
+        if collector_id.starts_with(source) {
+            format!("{}/{}/{}.{}", dir, day, collector_id, basename)
+        } else {
+            format!("{}/{}/{}.{}.{}", dir, day, &source, collector_id, basename)
         }
+    }
 
+    /// Group RIB files by the snapshot they belong to, in time order
+    pub fn group_by_snapshot(rib_files: &[RibFile]) -> BTreeMap<&str, Vec<&RibFile>> {
+        let mut snapshots = BTreeMap::<&str, Vec<&RibFile>>::new();
         for rib_file in rib_files {
-            download_file(&rib_file.url, Path::new(&rib_file.filename))
+            snapshots
+                .entry(rib_file.snapshot.as_str())
+                .or_default()
+                .push(rib_file);
         }
+        snapshots
     }
 
-    /// Return a list of availabe RIBs for a specific day (with details like download URL)
-    fn get_rib_list_for_day(date: &str, dir: &str) -> Vec<RibFile> {
-        let broker = BgpkitBroker::new().ts_start(date).ts_end(date);
-        let ribs = broker.daily_ribs().unwrap();
+    /// Return a list of availabe RIBs for a time range (with details like download URL)
+    pub fn get_rib_list_for_range(
+        start: &NaiveDateTime,
+        end: &NaiveDateTime,
+        hours: &[u32],
+        filter: &RibFilter,
+        dir: &str,
+    ) -> Vec<RibFile> {
+        let broker = BgpkitBroker::new()
+            .ts_start(start.format("%Y-%m-%dT%H:%M:%SZ"))
+            .ts_end(end.format("%Y-%m-%dT%H:%M:%SZ"))
+            .data_type("rib");
+        info!(
+            "Searching broker for MRT RIBs from {} to {} (hours {:?})",
+            start, end, hours
+        );
+        let ribs = broker.query().unwrap();
         debug!(
-            "Found {} MRT files via broker for date {}",
+            "Found {} MRT files via broker from {} to {}",
             ribs.len(),
-            date
+            start,
+            end
         );
 
         let mut rib_files = Vec::<RibFile>::new();
         for rib in ribs {
-            let basename = Path::new(&rib.url).file_name().unwrap().to_str().unwrap();
+            if !is_snapshot_hour(&rib.ts_start, hours) {
+                continue;
+            }
+            if !filter.matches(&rib.collector_id) {
+                debug!("Skipping filtered collector {}", rib.collector_id);
+                continue;
+            }
 
-            let source = if rib.collector_id.starts_with("rrc") {
-                String::from("ris")
-            } else {
-                String::from("route-views")
-            };
-
-            let filename = if rib.collector_id.starts_with(&source) {
-                format!("{}/{}.{}", dir, rib.collector_id, basename)
-            } else {
-                format!("{}/{}.{}.{}", dir, &source, rib.collector_id, basename)
-            };
+            let basename = Path::new(&rib.url).file_name().unwrap().to_str().unwrap();
+            let filename = get_rib_filename(dir, &rib.collector_id, &rib.ts_start, basename);
 
             rib_files.push(RibFile {
                 url: rib.url,
                 filename,
+                collector_id: rib.collector_id,
+                snapshot: get_snapshot(&rib.ts_start),
+                size: if rib.exact_size > 0 {
+                    Some(rib.exact_size as u64)
+                } else {
+                    None
+                },
             });
         }
 
         rib_files
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        #[test]
+        fn test_get_collector_from_filename() {
+            let day = NaiveDate::from_ymd_opt(2025, 9, 22)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap();
+            for (collector_id, basename) in [
+                ("rrc00", "bview.20250922.0000.gz"),
+                ("rrc00", "latest-bview.gz"),
+                ("route-views2", "rib.20250922.0000.bz2"),
+                ("route-views.amsix", "rib.20250922.0000.bz2"),
+                ("route-views.sg", "updates.20250922.0000.bz2"),
+            ] {
+                let filename = get_rib_filename("./mrts", collector_id, &day, basename);
+                assert_eq!(get_collector_from_filename(&filename), collector_id);
+            }
+
+            // Raw dump names, in the archive layouts or on their own
+            for (filename, collector_id) in [
+                (
+                    "https://data.ris.ripe.net/rrc00/2025.09/bview.20250922.0000.gz",
+                    "rrc00",
+                ),
+                ("/mirror/rrc01/latest-bview.gz", "rrc01"),
+                (
+                    "https://routeviews.org/route-views.amsix/bgpdata/2025.09/RIBS/rib.20250922.0000.bz2",
+                    "route-views.amsix",
+                ),
+                (
+                    "https://routeviews.org/bgpdata/2025.09/RIBS/rib.20250922.0000.bz2",
+                    "route-views2",
+                ),
+                ("rib.20250922.0000.bz2", "rib.20250922.0000.bz2"),
+                ("./a/bview.20250922.0000.gz", "./a/bview.20250922.0000.gz"),
+                ("latest-bview.gz", "latest-bview.gz"),
+                ("-", "-"),
+            ] {
+                assert_eq!(get_collector_from_filename(filename), collector_id);
+            }
+        }
+
+        #[test]
+        fn test_is_snapshot_hour() {
+            let timestamp =
+                |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+            assert!(is_snapshot_hour(&timestamp("2025-09-22 00:00"), &[0]));
+            assert!(is_snapshot_hour(
+                &timestamp("2025-09-22 16:00"),
+                &[0, 8, 16]
+            ));
+            assert!(!is_snapshot_hour(
+                &timestamp("2025-09-22 02:00"),
+                &[0, 8, 16]
+            ));
+            assert!(!is_snapshot_hour(
+                &timestamp("2025-09-22 08:15"),
+                &[0, 8, 16]
+            ));
+            assert!(!is_snapshot_hour(&timestamp("2025-09-22 00:00"), &[]));
+        }
+
+        #[test]
+        fn test_get_snapshot_from_filename() {
+            for (filename, snapshot) in [
+                ("rib.20250922.0800.bz2", "2025-09-22 08:00"),
+                (
+                    "./mrts/2025-09-22/ris.rrc00.bview.20250922.1600.gz",
+                    "2025-09-22 16:00",
+                ),
+                (
+                    "route-views.amsix.rib.20250922.0000.bz2",
+                    "2025-09-22 00:00",
+                ),
+                ("latest-bview.gz", ""),
+                ("updates.20250922.0000.gz", ""),
+                ("-", ""),
+            ] {
+                assert_eq!(get_snapshot_from_filename(filename), snapshot);
+            }
+        }
+
+        #[test]
+        fn test_glob_match() {
+            assert!(glob_match("rrc00", "rrc00"));
+            assert!(!glob_match("rrc00", "rrc01"));
+            assert!(glob_match("rrc0*", "rrc01"));
+            assert!(glob_match("route-views*", "route-views.amsix"));
+            assert!(glob_match("*amsix", "route-views.amsix"));
+            assert!(glob_match("rrc?5", "rrc25"));
+            assert!(!glob_match("rrc?5", "rrc256"));
+            assert!(glob_match("*", "anything"));
+        }
+
+        #[test]
+        fn test_rib_filter() {
+            let filter = RibFilter::default();
+            assert!(filter.matches("rrc00"));
+
+            let filter = RibFilter {
+                include: Vec::new(),
+                exclude: Vec::new(),
+                project: Some(String::from("ris")),
+            };
+            assert!(filter.matches("rrc00"));
+            assert!(!filter.matches("route-views2"));
+
+            let filter = RibFilter {
+                include: Vec::from([String::from("route-views*"), String::from("rrc00")]),
+                exclude: Vec::from([String::from("route-views.amsix")]),
+                project: None,
+            };
+            assert!(filter.matches("rrc00"));
+            assert!(!filter.matches("rrc01"));
+            assert!(filter.matches("route-views2"));
+            assert!(!filter.matches("route-views.amsix"));
+        }
+    }
 }
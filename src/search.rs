@@ -1,8 +1,38 @@
 pub mod path_search {
 
+    use crate::as_relationships::as_rel::AsRelationships;
     use crate::mrt_paths::path_data::PathData;
+    use crate::report::path_report;
+    use crate::search_aggregates::aggregate_search::find_hidden_origins;
+    use crate::search_aggregators::aggregator_search::find_aggregator_mismatches;
+    use crate::search_communities::community_search::find_communities_without_asn;
+    use crate::search_peers::peer_search::find_peer_first_hops;
+    use crate::search_special_asns::special_asn_search::find_special_asns;
+    use crate::search_valley_free::valley_free_search::{
+        find_divergence_links, find_valley_free_violations,
+    };
 
-    pub fn find_paths(path_data: &PathData) {
-        path_data.find_origins_with_divergent_paths();
+    /// Run every search and report the findings, the valley-free searches
+    /// only run if AS relationships were loaded
+    pub fn find_paths(path_data: &PathData, as_relationships: Option<&AsRelationships>) {
+        let divergent_paths = path_data.find_origins_with_divergent_paths();
+        path_report::print_divergent_paths(&divergent_paths);
+        path_report::print_as_trans_paths(&path_data.find_as_trans_paths());
+        path_report::print_communities_without_asn(&find_communities_without_asn(path_data));
+        path_report::print_peer_first_hops(&find_peer_first_hops(path_data));
+        path_report::print_special_asns(&find_special_asns(path_data));
+        path_report::print_hidden_origins(&find_hidden_origins(path_data));
+        path_report::print_aggregator_mismatches(&find_aggregator_mismatches(path_data));
+
+        if let Some(as_relationships) = as_relationships {
+            path_report::print_valley_free_violations(&find_valley_free_violations(
+                path_data,
+                as_relationships,
+            ));
+            path_report::print_divergence_links(&find_divergence_links(
+                &divergent_paths,
+                as_relationships,
+            ));
+        }
     }
 }
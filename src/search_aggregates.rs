@@ -0,0 +1,146 @@
+pub mod aggregate_search {
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::Asn;
+    use ipnet::IpNet;
+    use log::info;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// An aggregate route whose AS set hides the origins of the prefixes it covers,
+    /// cross-referenced with the more-specific prefixes seen without an AS set
+    #[derive(Debug)]
+    pub struct HiddenOrigins<'a> {
+        pub route: &'a Route,
+        /// Every ASN in the route's AS sets
+        pub as_set_asns: BTreeSet<Asn>,
+        /// More-specifics of the aggregate prefix, with their origins
+        pub more_specifics: BTreeMap<IpNet, BTreeSet<Asn>>,
+        /// AS set ASNs which originate a more-specific, the origins the AS set hides
+        pub confirmed_origins: BTreeSet<Asn>,
+        /// AS set ASNs which don't originate any more-specific
+        pub unconfirmed_origins: BTreeSet<Asn>,
+    }
+
+    /// Index the origins of every prefix routed without an AS set.
+    /// Only origins which survived filtering (origins with a single AS path are
+    /// removed after parsing, unless --no-filter is given) are indexed.
+    fn get_prefix_origins(path_data: &PathData) -> BTreeMap<IpNet, BTreeSet<Asn>> {
+        let mut prefix_origins = BTreeMap::<IpNet, BTreeSet<Asn>>::new();
+        for origin_as_paths in path_data.get_as_paths() {
+            for as_path in origin_as_paths.get_as_paths() {
+                for route in as_path.get_routes() {
+                    prefix_origins
+                        .entry(*route.get_prefix())
+                        .or_default()
+                        .insert(*origin_as_paths.get_origin());
+                }
+            }
+        }
+        prefix_origins
+    }
+
+    /// Return the prefixes within the aggregate, excluding the aggregate itself.
+    /// IpNet sorts by address then length, so every more-specific is between the
+    /// aggregate and the last address of the aggregate at the maximum length.
+    fn get_more_specifics(
+        prefix_origins: &BTreeMap<IpNet, BTreeSet<Asn>>,
+        aggregate: &IpNet,
+    ) -> BTreeMap<IpNet, BTreeSet<Asn>> {
+        let start = aggregate.trunc();
+        let end = IpNet::new(aggregate.broadcast(), aggregate.max_prefix_len()).unwrap();
+        prefix_origins
+            .range(start..=end)
+            .filter(|(prefix, _)| {
+                prefix.prefix_len() > aggregate.prefix_len() && aggregate.contains(*prefix)
+            })
+            .map(|(prefix, origins)| (*prefix, origins.clone()))
+            .collect()
+    }
+
+    /// Search all AS set routes for aggregates, and find which AS set ASNs originate
+    /// more-specifics of the aggregate elsewhere in PathData
+    pub fn find_hidden_origins(path_data: &PathData) -> Vec<HiddenOrigins<'_>> {
+        info!("Searching for aggregates with an AS set hiding the origins");
+
+        let prefix_origins = get_prefix_origins(path_data);
+        let mut found = Vec::new();
+        for route in path_data.get_as_set_routes() {
+            let as_set_asns: BTreeSet<Asn> = route
+                .get_as_sets()
+                .iter()
+                .flat_map(|as_set| as_set.asns.iter().copied())
+                .collect();
+            let more_specifics = get_more_specifics(&prefix_origins, route.get_prefix());
+            let more_specific_origins: BTreeSet<Asn> =
+                more_specifics.values().flatten().copied().collect();
+            let (confirmed_origins, unconfirmed_origins) = as_set_asns
+                .iter()
+                .copied()
+                .partition(|asn| more_specific_origins.contains(asn));
+
+            found.push(HiddenOrigins {
+                route,
+                as_set_asns,
+                more_specifics,
+                confirmed_origins,
+                unconfirmed_origins,
+            });
+        }
+
+        info!(
+            "Found {} AS set routes, {} with more-specifics from an AS set ASN",
+            found.len(),
+            found
+                .iter()
+                .filter(|hidden| !hidden.confirmed_origins.is_empty())
+                .count()
+        );
+        found
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn get_route(as_path: &[u32], as_set: &[u32], prefix: &str) -> Route {
+            Route::mock(None)
+                .with_as_path(as_path)
+                .with_as_set(as_set)
+                .with_prefix(prefix)
+                .build()
+        }
+
+        #[test]
+        fn test_find_hidden_origins() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(get_route(&[1, 2], &[3, 4], "198.51.0.0/16"));
+            path_data.insert_route(get_route(&[1, 3], &[], "198.51.100.0/24"));
+            path_data.insert_route(get_route(&[1, 5], &[], "198.51.101.0/24"));
+            // Outside of the aggregate, and the aggregate itself
+            path_data.insert_route(get_route(&[1, 4], &[], "198.52.0.0/24"));
+            path_data.insert_route(get_route(&[1, 4], &[], "198.51.0.0/16"));
+
+            // AS set routes aren't grouped by origin
+            assert_eq!(path_data.get_as_set_routes().len(), 1);
+            assert_eq!(path_data.get_origins_count(), 3);
+
+            let found = find_hidden_origins(&path_data);
+            assert_eq!(found.len(), 1);
+            assert_eq!(
+                found[0].more_specifics.keys().collect::<Vec<_>>(),
+                Vec::from([
+                    &"198.51.100.0/24".parse::<IpNet>().unwrap(),
+                    &"198.51.101.0/24".parse::<IpNet>().unwrap(),
+                ])
+            );
+            assert_eq!(
+                found[0].confirmed_origins,
+                BTreeSet::from([Asn::new_32bit(3)])
+            );
+            assert_eq!(
+                found[0].unconfirmed_origins,
+                BTreeSet::from([Asn::new_32bit(4)])
+            );
+        }
+    }
+}
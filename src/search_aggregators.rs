@@ -0,0 +1,115 @@
+pub mod aggregator_search {
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::Asn;
+    use log::info;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::net::Ipv4Addr;
+
+    /// How the aggregator ASN of a route relates to its AS path
+    #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    pub enum AggregatorMismatch {
+        /// Not in the AS path, AS sets or confederation segments
+        AbsentFromPath,
+        /// In the path, but not the origin
+        NotOrigin,
+    }
+
+    /// Routes aggregated by one ASN, whose aggregator doesn't match the AS path
+    #[derive(Debug)]
+    pub struct AggregatorRoutes<'a> {
+        pub aggregator_asn: Asn,
+        /// BGP identifiers of the aggregating routers
+        pub aggregator_ips: BTreeSet<Ipv4Addr>,
+        pub absent_from_path: Vec<&'a Route>,
+        pub not_origin: Vec<&'a Route>,
+    }
+
+    /// Return how the route's aggregator ASN doesn't match the AS path, if it doesn't.
+    /// AS set routes have no single origin, so the aggregator only has to be present.
+    pub fn get_aggregator_mismatch(route: &Route) -> Option<AggregatorMismatch> {
+        let asn = route.get_aggregator().as_ref()?.asn;
+        let in_path = route.get_as_path().contains(&asn)
+            || route
+                .get_as_sets()
+                .iter()
+                .any(|as_set| as_set.asns.contains(&asn))
+            || route
+                .get_confed_segments()
+                .iter()
+                .any(|c| c.segment.asns.contains(&asn));
+
+        if !in_path {
+            Some(AggregatorMismatch::AbsentFromPath)
+        } else if !route.has_as_sets() && *route.get_origin() != asn {
+            Some(AggregatorMismatch::NotOrigin)
+        } else {
+            None
+        }
+    }
+
+    /// Search all routes (including AS set routes) for an aggregator ASN which is
+    /// missing from the AS path or isn't the origin, grouped by aggregator ASN
+    pub fn find_aggregator_mismatches(path_data: &PathData) -> Vec<AggregatorRoutes<'_>> {
+        info!("Searching for aggregators which aren't in the AS path or aren't the origin");
+
+        let mut aggregators = BTreeMap::<Asn, AggregatorRoutes>::new();
+        for route in path_data.get_routes() {
+            let Some(mismatch) = get_aggregator_mismatch(route) else {
+                continue;
+            };
+            let aggregator = route.get_aggregator().unwrap();
+            let found = aggregators
+                .entry(aggregator.asn)
+                .or_insert_with(|| AggregatorRoutes {
+                    aggregator_asn: aggregator.asn,
+                    aggregator_ips: BTreeSet::new(),
+                    absent_from_path: Vec::new(),
+                    not_origin: Vec::new(),
+                });
+            found.aggregator_ips.insert(aggregator.ip);
+            match mismatch {
+                AggregatorMismatch::AbsentFromPath => found.absent_from_path.push(route),
+                AggregatorMismatch::NotOrigin => found.not_origin.push(route),
+            }
+        }
+
+        info!(
+            "Found {} aggregators which don't match the AS path",
+            aggregators.len()
+        );
+        aggregators.into_values().collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn get_route(as_path: &[u32], aggregator: Option<u32>, prefix: &str) -> Route {
+            let route = Route::mock(None).with_as_path(as_path).with_prefix(prefix);
+            match aggregator {
+                Some(asn) => route.with_aggregator(asn),
+                None => route,
+            }
+            .build()
+        }
+
+        #[test]
+        fn test_find_aggregator_mismatches() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(get_route(&[1, 2, 3], Some(3), "198.51.100.0/24"));
+            path_data.insert_route(get_route(&[1, 2, 3], None, "198.51.101.0/24"));
+            path_data.insert_route(get_route(&[1, 2, 3], Some(2), "198.51.102.0/24"));
+            path_data.insert_route(get_route(&[1, 2, 3], Some(4), "198.51.103.0/24"));
+            path_data.insert_route(get_route(&[1, 5, 3], Some(4), "198.51.104.0/24"));
+
+            let found = find_aggregator_mismatches(&path_data);
+            assert_eq!(found.len(), 2);
+            assert_eq!(found[0].aggregator_asn, Asn::new_32bit(2));
+            assert_eq!(found[0].not_origin.len(), 1);
+            assert!(found[0].absent_from_path.is_empty());
+            assert_eq!(found[1].aggregator_asn, Asn::new_32bit(4));
+            assert_eq!(found[1].absent_from_path.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+pub mod community_search {
+    use crate::mrt_asn::asn::is_special_purpose;
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::{Asn, Community, Peer};
+    use log::info;
+    use std::collections::BTreeSet;
+
+    #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    pub enum CommunityType {
+        Standard,
+        Large,
+    }
+
+    /// A route carrying a community set by an ASN which isn't in the route's AS path,
+    /// the ASN may have been hidden from the path.
+    #[derive(Debug)]
+    pub struct CommunityWithoutAsn<'a> {
+        pub missing_asn: Asn,
+        pub community_type: CommunityType,
+        pub peer: &'a Peer,
+        pub filename: &'a String,
+        pub route: &'a Route,
+    }
+
+    /// Return the ASNs from the route's communities and large communities which
+    /// aren't in the AS path (or a confederation segment). Special purpose ASNs
+    /// and well-known communities are skipped.
+    fn get_missing_asns(route: &Route) -> Vec<(Asn, CommunityType)> {
+        let mut asns = BTreeSet::<(u32, CommunityType)>::new();
+
+        for community in route.get_communities() {
+            if let Community::Custom(asn, _) = community {
+                asns.insert((asn.to_u32(), CommunityType::Standard));
+            }
+        }
+        for large_community in route.get_large_communities() {
+            asns.insert((large_community.global_admin, CommunityType::Large));
+        }
+
+        asns.into_iter()
+            .map(|(asn, community_type)| (Asn::new_32bit(asn), community_type))
+            .filter(|(asn, _)| !is_special_purpose(asn))
+            .filter(|(asn, _)| !route.get_as_path().contains(asn))
+            .filter(|(asn, _)| {
+                !route
+                    .get_confed_segments()
+                    .iter()
+                    .any(|c| c.segment.asns.contains(asn))
+            })
+            .collect()
+    }
+
+    /// Search all routes (including AS set routes) for communities whose ASN isn't
+    /// in the AS path
+    pub fn find_communities_without_asn(path_data: &PathData) -> Vec<CommunityWithoutAsn<'_>> {
+        info!("Searching for communities without their ASN in the AS path");
+
+        let mut found = Vec::new();
+        for route in path_data.get_routes() {
+            for (missing_asn, community_type) in get_missing_asns(route) {
+                found.push(CommunityWithoutAsn {
+                    missing_asn,
+                    community_type,
+                    peer: route.get_peer(),
+                    filename: route.get_filename(),
+                    route,
+                });
+            }
+        }
+
+        info!(
+            "Found {} communities without their ASN in the AS path",
+            found.len()
+        );
+        found
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mrt_asn::asn::Testing as AsnTesting;
+        use bgpkit_parser::models::LargeCommunity;
+
+        fn get_route(communities: Vec<Community>, large_communities: Vec<LargeCommunity>) -> Route {
+            Route::mock(None)
+                .with_as_path(&[1, 2, 3])
+                .with_prefix("198.51.100.0/24")
+                .with_communities(communities)
+                .with_large_communities(large_communities)
+                .build()
+        }
+
+        #[test]
+        fn test_find_communities_without_asn() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(get_route(
+                Vec::from([
+                    Community::Custom(Asn::get_mock(Some(2)), 100),
+                    Community::Custom(Asn::get_mock(Some(4)), 100),
+                    Community::Custom(Asn::get_mock(Some(65000)), 100),
+                    Community::NoExport,
+                ]),
+                Vec::from([
+                    LargeCommunity::new(5, [1, 1]),
+                    LargeCommunity::new(4200000000, [1, 1]),
+                ]),
+            ));
+
+            let found = find_communities_without_asn(&path_data);
+            let missing: Vec<(Asn, CommunityType)> = found
+                .iter()
+                .map(|f| (f.missing_asn, f.community_type))
+                .collect();
+            assert_eq!(
+                missing,
+                Vec::from([
+                    (Asn::get_mock(Some(4)), CommunityType::Standard),
+                    (Asn::get_mock(Some(5)), CommunityType::Large),
+                ])
+            );
+            assert_eq!(found[0].filename, "unit test");
+        }
+
+        #[test]
+        fn test_find_communities_in_as_set_route() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(
+                Route::mock(None)
+                    .with_as_path(&[1, 2])
+                    .with_as_set(&[3, 4])
+                    .with_communities(Vec::from([Community::Custom(Asn::get_mock(Some(5)), 100)]))
+                    .with_large_communities(Vec::new())
+                    .build(),
+            );
+
+            let found = find_communities_without_asn(&path_data);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].missing_asn, Asn::get_mock(Some(5)));
+            assert!(found[0].route.has_as_sets());
+        }
+    }
+}
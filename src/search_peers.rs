@@ -0,0 +1,307 @@
+pub mod peer_search {
+    use crate::mrt_as_path_segments::as_path_segments::ConfedSegmentType;
+    use crate::mrt_interner::interner::PeerId;
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::Peer;
+    use log::info;
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    /// Peers with fewer routes than this aren't reported as hiding themselves
+    pub const MIN_HIDING_ROUTES: usize = 100;
+
+    /// The fraction of a peer's routes which must be missing the peer ASN
+    /// for it to be reported as hiding itself
+    pub const MIN_HIDING_RATIO: f64 = 0.95;
+
+    /// Where the peer ASN appears in the AS path of a route learned from that peer
+    #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    pub enum FirstHopType {
+        /// The first hop is the peer, as expected for an eBGP session
+        Peer,
+        /// The peer isn't in the path, e.g. an IXP route server or a peer
+        /// which strips its own ASN (or an iBGP session with the collector)
+        PeerAbsent,
+        /// The peer is in the path, but not as the first hop
+        PeerElsewhere,
+    }
+
+    /// Number of routes of each first hop type
+    #[derive(Clone, Debug, Default, Eq, PartialEq)]
+    pub struct FirstHopCounts {
+        pub peer: usize,
+        pub peer_absent: usize,
+        pub peer_elsewhere: usize,
+    }
+
+    impl FirstHopCounts {
+        fn add(&mut self, first_hop_type: FirstHopType) {
+            match first_hop_type {
+                FirstHopType::Peer => self.peer += 1,
+                FirstHopType::PeerAbsent => self.peer_absent += 1,
+                FirstHopType::PeerElsewhere => self.peer_elsewhere += 1,
+            }
+        }
+
+        pub fn get_total(&self) -> usize {
+            self.peer + self.peer_absent + self.peer_elsewhere
+        }
+
+        /// Enough routes, and nearly all of them are missing the peer ASN
+        pub fn is_hiding(&self) -> bool {
+            let total = self.get_total();
+            total >= MIN_HIDING_ROUTES
+                && (self.peer_absent as f64) >= (total as f64) * MIN_HIDING_RATIO
+        }
+    }
+
+    /// The first hop counts of all routes from one peer
+    #[derive(Debug)]
+    pub struct PeerFirstHops<'a> {
+        pub peer: &'a Peer,
+        /// Collectors the peer's routes were seen on
+        pub collectors: BTreeSet<&'a str>,
+        pub counts: FirstHopCounts,
+    }
+
+    /// First hop counts aggregated per peer and per collector.
+    /// The collector is part of a route's identity (see Route), so a route which was seen
+    /// by more than one collector is counted for each of them.
+    #[derive(Debug, Default)]
+    pub struct FirstHopReport<'a> {
+        /// Sorted by peer ASN then peer IP
+        pub peers: Vec<PeerFirstHops<'a>>,
+        pub collectors: BTreeMap<&'a str, FirstHopCounts>,
+    }
+
+    impl<'a> FirstHopReport<'a> {
+        /// Peers which systematically leave their own ASN out of the path
+        pub fn get_hiding_peers(&self) -> Vec<&PeerFirstHops<'a>> {
+            self.peers
+                .iter()
+                .filter(|peer_first_hops| peer_first_hops.counts.is_hiding())
+                .collect()
+        }
+    }
+
+    /// Compare the peer ASN with the first hop of the raw AS path. A confederation
+    /// segment at the start of the path (the peer is a member AS of the collector's
+    /// confederation) comes before the AS sequence, so its first ASN is the first hop.
+    pub fn get_first_hop_type(route: &Route) -> FirstHopType {
+        let peer_asn = route.get_peer().peer_asn;
+        let as_path = route.get_as_path();
+        let confed_segments = route.get_confed_segments();
+        let is_first_hop = match confed_segments
+            .first()
+            .filter(|confed_segment| confed_segment.segment.position == 0)
+        {
+            Some(confed_segment) => match confed_segment.segment_type {
+                ConfedSegmentType::Sequence => {
+                    confed_segment.segment.asns.first() == Some(&peer_asn)
+                }
+                ConfedSegmentType::Set => confed_segment.segment.asns.contains(&peer_asn),
+            },
+            None => as_path.first() == Some(&peer_asn),
+        };
+
+        if is_first_hop {
+            FirstHopType::Peer
+        } else if as_path.contains(&peer_asn)
+            || confed_segments
+                .iter()
+                .any(|confed_segment| confed_segment.segment.asns.contains(&peer_asn))
+        {
+            FirstHopType::PeerElsewhere
+        } else {
+            FirstHopType::PeerAbsent
+        }
+    }
+
+    /// Classify the first hop of every route (including AS set routes), aggregated
+    /// per peer and per collector
+    pub fn find_peer_first_hops(path_data: &PathData) -> FirstHopReport<'_> {
+        info!("Searching for peers which aren't the first hop in the AS path");
+
+        let mut peers = HashMap::<PeerId, PeerFirstHops>::new();
+        let mut collectors = BTreeMap::<&str, FirstHopCounts>::new();
+        for route in path_data.get_routes() {
+            let first_hop_type = get_first_hop_type(route);
+            let collector = route.get_collector().as_str();
+
+            let peer_first_hops =
+                peers
+                    .entry(route.get_peer_id())
+                    .or_insert_with(|| PeerFirstHops {
+                        peer: route.get_peer(),
+                        collectors: BTreeSet::new(),
+                        counts: FirstHopCounts::default(),
+                    });
+            peer_first_hops.collectors.insert(collector);
+            peer_first_hops.counts.add(first_hop_type);
+
+            collectors.entry(collector).or_default().add(first_hop_type);
+        }
+
+        let mut peers: Vec<PeerFirstHops> = peers.into_values().collect();
+        peers.sort_by_key(|p| (p.peer.peer_asn, p.peer.peer_ip));
+
+        let report = FirstHopReport { peers, collectors };
+        info!(
+            "Found {} peers, {} hiding themselves",
+            report.peers.len(),
+            report.get_hiding_peers().len()
+        );
+        report
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mrt_as_path_segments::as_path_segments::{ConfedSegment, PositionedSegment};
+        use bgpkit_parser::models::Asn;
+        use std::net::Ipv4Addr;
+
+        fn get_route(as_path: &[u32], peer_asn: u32, filename: &str, prefix: u32) -> Route {
+            Route::mock(None)
+                .with_as_path(as_path)
+                .with_peer(peer_asn)
+                .with_filename(filename)
+                .with_prefix(&format!("{}/24", Ipv4Addr::from(prefix << 8)))
+                .build()
+        }
+
+        #[test]
+        fn test_find_peer_first_hops() {
+            let mut path_data = PathData::new();
+            for prefix in 0..MIN_HIDING_ROUTES as u32 {
+                // Peer 1 is always the first hop, peer 2 is a route server
+                path_data.insert_route(get_route(&[1, 3, 4], 1, "ris.rrc00.bview", prefix));
+                path_data.insert_route(get_route(&[3, 4], 2, "route-views2.rib", prefix));
+            }
+            path_data.insert_route(get_route(&[5, 1, 4], 1, "ris.rrc01.bview", 0));
+
+            let report = find_peer_first_hops(&path_data);
+            assert_eq!(report.peers.len(), 2);
+            assert_eq!(
+                report.peers[0].counts,
+                FirstHopCounts {
+                    peer: MIN_HIDING_ROUTES,
+                    peer_absent: 0,
+                    peer_elsewhere: 1,
+                }
+            );
+            assert_eq!(
+                report.peers[0].collectors,
+                BTreeSet::from(["rrc00", "rrc01"])
+            );
+            assert_eq!(
+                report.collectors["route-views2"].peer_absent,
+                MIN_HIDING_ROUTES
+            );
+
+            let hiding_peers = report.get_hiding_peers();
+            assert_eq!(hiding_peers.len(), 1);
+            assert_eq!(hiding_peers[0].peer.peer_asn, Asn::new_32bit(2));
+        }
+
+        #[test]
+        fn test_find_peer_first_hops_in_as_set_route() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(
+                Route::mock(None)
+                    .with_as_path(&[2, 3])
+                    .with_as_set(&[4, 5])
+                    .with_peer(1)
+                    .build(),
+            );
+
+            let report = find_peer_first_hops(&path_data);
+            assert_eq!(report.peers.len(), 1);
+            assert_eq!(report.peers[0].counts.peer_absent, 1);
+        }
+
+        #[test]
+        fn test_get_first_hop_type_with_confed_segments() {
+            let confed_segment = |segment_type, asns: &[u32]| ConfedSegment {
+                segment_type,
+                segment: PositionedSegment {
+                    position: 0,
+                    asns: asns.iter().map(|asn| Asn::new_32bit(*asn)).collect(),
+                },
+            };
+            let first_hop_type = |confed_segments: Vec<ConfedSegment>, peer_asn| {
+                get_first_hop_type(
+                    &Route::mock(None)
+                        .with_as_path(&[10, 3, 4])
+                        .with_confed_segments(confed_segments)
+                        .with_peer(peer_asn)
+                        .build(),
+                )
+            };
+
+            // A confederation member peer, its member AS is first, not the AS sequence
+            let sequence =
+                || Vec::from([confed_segment(ConfedSegmentType::Sequence, &[65001, 65002])]);
+            assert_eq!(first_hop_type(sequence(), 65001), FirstHopType::Peer);
+            assert_eq!(
+                first_hop_type(sequence(), 65002),
+                FirstHopType::PeerElsewhere
+            );
+            assert_eq!(first_hop_type(sequence(), 10), FirstHopType::PeerElsewhere);
+            assert_eq!(first_hop_type(sequence(), 7), FirstHopType::PeerAbsent);
+
+            let set = || Vec::from([confed_segment(ConfedSegmentType::Set, &[65001, 65002])]);
+            assert_eq!(first_hop_type(set(), 65002), FirstHopType::Peer);
+
+            // A confederation segment after the first hop
+            let mut later = sequence();
+            later[0].segment.position = 1;
+            assert_eq!(first_hop_type(later.clone(), 10), FirstHopType::Peer);
+            assert_eq!(first_hop_type(later, 65001), FirstHopType::PeerElsewhere);
+            assert_eq!(first_hop_type(Vec::new(), 10), FirstHopType::Peer);
+        }
+
+        #[test]
+        fn test_find_peer_first_hops_per_collector() {
+            let mut path_data = PathData::new();
+            // The same route from a peer of two collectors, and from a second file of one
+            for filename in [
+                "ris.rrc00.bview.a",
+                "ris.rrc01.bview.a",
+                "ris.rrc00.bview.b",
+            ] {
+                path_data.insert_route(get_route(&[1, 3, 4], 1, filename, 0));
+            }
+
+            let report = find_peer_first_hops(&path_data);
+            assert_eq!(report.peers[0].counts.peer, 2);
+            assert_eq!(report.collectors["rrc00"].peer, 1);
+            assert_eq!(report.collectors["rrc01"].peer, 1);
+        }
+
+        #[test]
+        fn test_find_peer_first_hops_same_filename() {
+            // RouteViews collectors all publish rib.<date>.<time>.bz2
+            let mut path_data = PathData::new();
+            for collector in ["route-views.amsix", "route-views.sg"] {
+                path_data.insert_route(
+                    Route::mock(None)
+                        .with_as_path(&[1, 3, 4])
+                        .with_peer(1)
+                        .with_filename("rib.20250922.0000.bz2")
+                        .with_collector(collector)
+                        .build(),
+                );
+            }
+
+            let report = find_peer_first_hops(&path_data);
+            assert_eq!(report.peers[0].counts.peer, 2);
+            assert_eq!(
+                report.peers[0].collectors,
+                BTreeSet::from(["route-views.amsix", "route-views.sg"])
+            );
+            assert_eq!(report.collectors.len(), 2);
+            assert_eq!(report.collectors["route-views.sg"].peer, 1);
+        }
+    }
+}
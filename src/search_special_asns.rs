@@ -0,0 +1,200 @@
+pub mod special_asn_search {
+    use crate::mrt_asn::asn::{SpecialPurposeType, get_special_purpose_type};
+    use crate::mrt_interner::interner::AsPathId;
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::Route;
+    use bgpkit_parser::models::Asn;
+    use indexmap::IndexMap;
+    use log::info;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// A run of consecutive special purpose ASNs in an AS path, with the public
+    /// ASNs on either side of it
+    #[derive(Debug, Eq, PartialEq)]
+    struct SpecialAsnRun {
+        /// The public ASN before the run (towards the peer), None if the run starts the path
+        left: Option<Asn>,
+        /// The public ASN after the run (towards the origin), None if the run ends the path
+        right: Option<Asn>,
+        special_asns: Vec<(Asn, SpecialPurposeType)>,
+        /// Every public ASN before the run, which propagated the path without removing it
+        transit_asns: Vec<Asn>,
+    }
+
+    /// AS paths with special purpose ASNs between the same pair of public neighbours
+    #[derive(Debug)]
+    pub struct SpecialAsnGroup<'a> {
+        pub left: Option<Asn>,
+        pub right: Option<Asn>,
+        pub special_asns: BTreeSet<(Asn, SpecialPurposeType)>,
+        /// Public ASNs before the special purpose ASNs in the paths, which let them through
+        pub transit_asns: BTreeSet<Asn>,
+        /// The routes, keyed by their deduped AS path
+        pub as_paths: IndexMap<AsPathId, Vec<&'a Route>>,
+    }
+
+    impl SpecialAsnGroup<'_> {
+        pub fn get_routes_count(&self) -> usize {
+            self.as_paths.values().map(|routes| routes.len()).sum()
+        }
+    }
+
+    fn get_special_asn_runs(as_path: &[Asn]) -> Vec<SpecialAsnRun> {
+        let mut runs = Vec::<SpecialAsnRun>::new();
+        let mut transit_asns = Vec::<Asn>::new();
+        let mut run: Option<SpecialAsnRun> = None;
+
+        for asn in as_path {
+            match get_special_purpose_type(asn) {
+                Some(special_purpose_type) => {
+                    run.get_or_insert_with(|| SpecialAsnRun {
+                        left: transit_asns.last().copied(),
+                        right: None,
+                        special_asns: Vec::new(),
+                        transit_asns: transit_asns.clone(),
+                    })
+                    .special_asns
+                    .push((*asn, special_purpose_type));
+                }
+                None => {
+                    if let Some(mut ended) = run.take() {
+                        ended.right = Some(*asn);
+                        runs.push(ended);
+                    }
+                    transit_asns.push(*asn);
+                }
+            }
+        }
+        runs.extend(run);
+        runs
+    }
+
+    /// Search the AS paths of all routes (including AS set routes) for private,
+    /// documentation and reserved ASNs at any position in the AS sequence, grouped by
+    /// the public ASNs on either side of them
+    pub fn find_special_asns(path_data: &PathData) -> Vec<SpecialAsnGroup<'_>> {
+        info!("Searching for private, documentation and reserved ASNs in AS paths");
+
+        let mut groups = BTreeMap::<(Option<Asn>, Option<Asn>), SpecialAsnGroup>::new();
+        for route in path_data.get_routes() {
+            for run in get_special_asn_runs(route.get_deduped_as_path()) {
+                let group =
+                    groups
+                        .entry((run.left, run.right))
+                        .or_insert_with(|| SpecialAsnGroup {
+                            left: run.left,
+                            right: run.right,
+                            special_asns: BTreeSet::new(),
+                            transit_asns: BTreeSet::new(),
+                            as_paths: IndexMap::new(),
+                        });
+                group.special_asns.extend(run.special_asns);
+                group.transit_asns.extend(run.transit_asns);
+                let routes = group
+                    .as_paths
+                    .entry(route.get_deduped_as_path_id())
+                    .or_default();
+                // A path can only have several runs with the same neighbours if it loops
+                if !routes.last().is_some_and(|last| std::ptr::eq(*last, route)) {
+                    routes.push(route);
+                }
+            }
+        }
+
+        info!(
+            "Found special purpose ASNs between {} pairs of public ASNs",
+            groups.len()
+        );
+        groups.into_values().collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_get_special_asn_runs() {
+            let as_path = Vec::from([1, 2, 64512, 23456, 3, 0].map(Asn::new_32bit));
+            let runs = get_special_asn_runs(&as_path);
+            assert_eq!(
+                runs,
+                Vec::from([
+                    SpecialAsnRun {
+                        left: Some(Asn::new_32bit(2)),
+                        right: Some(Asn::new_32bit(3)),
+                        special_asns: Vec::from([
+                            (Asn::new_32bit(64512), SpecialPurposeType::Private),
+                            (Asn::new_32bit(23456), SpecialPurposeType::Reserved),
+                        ]),
+                        transit_asns: Vec::from([1, 2].map(Asn::new_32bit)),
+                    },
+                    SpecialAsnRun {
+                        left: Some(Asn::new_32bit(3)),
+                        right: None,
+                        special_asns: Vec::from([(
+                            Asn::new_32bit(0),
+                            SpecialPurposeType::Reserved
+                        )]),
+                        transit_asns: Vec::from([1, 2, 3].map(Asn::new_32bit)),
+                    },
+                ])
+            );
+            assert!(get_special_asn_runs(&as_path[..2]).is_empty());
+        }
+
+        #[test]
+        fn test_find_special_asns() {
+            let mut path_data = PathData::new();
+            for as_path in [
+                [1, 64512, 3].as_slice(),
+                &[1, 64512, 64512, 3],
+                &[2, 1, 64513, 3],
+                &[64496, 1, 3],
+                &[4, 5, 3],
+            ] {
+                path_data.insert_route(Route::mock(None).with_as_path(as_path).build());
+            }
+
+            let groups = find_special_asns(&path_data);
+            assert_eq!(groups.len(), 2);
+            assert_eq!(groups[0].left, None);
+            assert_eq!(groups[0].right, Some(Asn::new_32bit(1)));
+            assert!(groups[0].transit_asns.is_empty());
+
+            assert_eq!(groups[1].left, Some(Asn::new_32bit(1)));
+            assert_eq!(groups[1].right, Some(Asn::new_32bit(3)));
+            assert_eq!(groups[1].as_paths.len(), 2);
+            assert_eq!(groups[1].get_routes_count(), 3);
+            assert_eq!(
+                groups[1].special_asns,
+                BTreeSet::from([
+                    (Asn::new_32bit(64512), SpecialPurposeType::Private),
+                    (Asn::new_32bit(64513), SpecialPurposeType::Private),
+                ])
+            );
+            assert_eq!(
+                groups[1].transit_asns,
+                BTreeSet::from([1, 2].map(Asn::new_32bit))
+            );
+        }
+
+        #[test]
+        fn test_find_special_asns_in_as_set_route() {
+            let mut path_data = PathData::new();
+            path_data.insert_route(
+                Route::mock(None)
+                    .with_as_path(&[1, 65000, 2])
+                    .with_as_set(&[3, 4])
+                    .build(),
+            );
+
+            let groups = find_special_asns(&path_data);
+            assert_eq!(groups.len(), 1);
+            assert_eq!(
+                groups[0].special_asns,
+                BTreeSet::from([(Asn::new_32bit(65000), SpecialPurposeType::Private)])
+            );
+            assert_eq!(groups[0].get_routes_count(), 1);
+        }
+    }
+}
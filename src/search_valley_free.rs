@@ -0,0 +1,158 @@
+pub mod valley_free_search {
+    use crate::as_relationships::as_rel::{AsRelationships, Relationship};
+    use crate::mrt_as_path::as_path::AsPath;
+    use crate::mrt_divergent_paths::divergent_paths::DivergentPaths;
+    use crate::mrt_paths::path_data::PathData;
+    use bgpkit_parser::models::Asn;
+    use log::info;
+
+    /// An AS path which isn't valley-free, given the known AS relationships
+    #[derive(Debug)]
+    pub struct ValleyFreeViolation<'a> {
+        pub as_path: &'a AsPath,
+        /// The first link (from, to) in the direction the route was propagated,
+        /// i.e. from the origin towards the peer, which breaks the valley-free rule
+        pub link: (Asn, Asn),
+        pub relationship: Relationship,
+    }
+
+    /// The adjacencies with no known relationship in the sub-paths of a divergence,
+    /// which may be a hidden link
+    #[derive(Debug)]
+    pub struct DivergenceLinks<'a> {
+        pub divergent_paths: &'a DivergentPaths<'a>,
+        pub unknown_links_a: Vec<(Asn, Asn)>,
+        pub unknown_links_b: Vec<(Asn, Asn)>,
+    }
+
+    impl DivergenceLinks<'_> {
+        pub fn has_unknown_link(&self) -> bool {
+            !self.unknown_links_a.is_empty() || !self.unknown_links_b.is_empty()
+        }
+    }
+
+    /// Walk the path from the origin, a route may only go up from customer to provider,
+    /// then across at most one peer link, then down from provider to customer.
+    /// Links with no known relationship are skipped.
+    /// Return the first link which breaks this, with its relationship.
+    fn get_valley(
+        as_path: &[Asn],
+        as_relationships: &AsRelationships,
+    ) -> Option<((Asn, Asn), Relationship)> {
+        let mut uphill = true;
+        for link in as_path.windows(2).rev() {
+            let (from, to) = (link[1], link[0]);
+            let Some(relationship) = as_relationships.get_relationship(&from, &to) else {
+                continue;
+            };
+            match relationship {
+                Relationship::CustomerToProvider | Relationship::PeerToPeer if !uphill => {
+                    return Some(((from, to), relationship));
+                }
+                Relationship::CustomerToProvider => {}
+                Relationship::PeerToPeer | Relationship::ProviderToCustomer => uphill = false,
+            }
+        }
+        None
+    }
+
+    /// Return each adjacent pair of ASNs with no known relationship, as (from, to)
+    /// in the direction the route was propagated
+    fn get_unknown_links(as_path: &[Asn], as_relationships: &AsRelationships) -> Vec<(Asn, Asn)> {
+        as_path
+            .windows(2)
+            .rev()
+            .map(|link| (link[1], link[0]))
+            .filter(|(from, to)| {
+                from != to && as_relationships.get_relationship(from, to).is_none()
+            })
+            .collect()
+    }
+
+    /// Check every (deduped) AS path for valley-free violations
+    pub fn find_valley_free_violations<'a>(
+        path_data: &'a PathData,
+        as_relationships: &AsRelationships,
+    ) -> Vec<ValleyFreeViolation<'a>> {
+        info!("Searching for AS paths which aren't valley-free");
+
+        let mut found = Vec::new();
+        for origin_as_paths in path_data.get_as_paths() {
+            for as_path in origin_as_paths.get_as_paths() {
+                if let Some((link, relationship)) =
+                    get_valley(as_path.get_as_path(), as_relationships)
+                {
+                    found.push(ValleyFreeViolation {
+                        as_path,
+                        link,
+                        relationship,
+                    });
+                }
+            }
+        }
+
+        info!("Found {} AS paths which aren't valley-free", found.len());
+        found
+    }
+
+    /// Find the links with no known relationship in the sub-paths of each divergence
+    pub fn find_divergence_links<'a>(
+        divergent_paths: &'a [DivergentPaths<'a>],
+        as_relationships: &AsRelationships,
+    ) -> Vec<DivergenceLinks<'a>> {
+        info!("Searching divergent paths for links with no known relationship");
+
+        let found: Vec<DivergenceLinks> = divergent_paths
+            .iter()
+            .map(|divergent| DivergenceLinks {
+                divergent_paths: divergent,
+                unknown_links_a: get_unknown_links(&divergent.sub_path_a, as_relationships),
+                unknown_links_b: get_unknown_links(&divergent.sub_path_b, as_relationships),
+            })
+            .collect();
+
+        info!(
+            "Found {} of {} divergences with a link with no known relationship",
+            found
+                .iter()
+                .filter(|links| links.has_unknown_link())
+                .count(),
+            found.len()
+        );
+        found
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn get_path(asns: &[u32]) -> Vec<Asn> {
+            asns.iter().map(|a| Asn::new_32bit(*a)).collect()
+        }
+
+        #[test]
+        fn test_get_valley() {
+            // 1 and 2 are providers of 3, 1 peers with 2, 4 is a customer of 2
+            let as_relationships =
+                AsRelationships::from_reader("1|3|-1\n2|3|-1\n1|2|0\n2|4|-1\n".as_bytes());
+
+            // Up, across and down, from origin 4
+            assert!(get_valley(&get_path(&[3, 1, 2, 4]), &as_relationships).is_none());
+            // Unknown links are skipped
+            assert!(get_valley(&get_path(&[5, 3, 1, 2, 4]), &as_relationships).is_none());
+            // Down from 1 to 3, then up from 3 to 2
+            assert_eq!(
+                get_valley(&get_path(&[2, 3, 1]), &as_relationships),
+                Some((
+                    (Asn::new_32bit(3), Asn::new_32bit(2)),
+                    Relationship::CustomerToProvider
+                ))
+            );
+
+            assert_eq!(
+                get_unknown_links(&get_path(&[5, 3, 1]), &as_relationships),
+                Vec::from([(Asn::new_32bit(3), Asn::new_32bit(5))])
+            );
+        }
+    }
+}
@@ -0,0 +1,962 @@
+pub mod path_snapshot {
+    use crate::mrt_aggregator::aggregator::Aggregator;
+    use crate::mrt_as_path::as_path::AsPath;
+    use crate::mrt_as_path_segments::as_path_segments::{
+        ConfedSegment, ConfedSegmentType, PositionedSegment, RawAsPaths,
+    };
+    use crate::mrt_community::shared_communities::SharedCommunities;
+    use crate::mrt_interner::interner::{AsPathId, PeerId, RawAsPathsId};
+    use crate::mrt_large_community::shared_large_communities::SharedLargeCommunities;
+    use crate::mrt_origin_as_paths::origin_as_paths::OriginAsPaths;
+    use crate::mrt_paths::path_data::PathData;
+    use crate::mrt_route::route::{Route, RouteSource};
+    use bgpkit_parser::models::{
+        AsPath as BgpAsPath, AsPathSegment, Asn, Community, LargeCommunity, Peer, PeerType,
+    };
+    use indexmap::IndexSet;
+    use ipnet::IpNet;
+    use log::{info, warn};
+    use std::fs::File;
+    use std::hash::Hash;
+    use std::io::{self, BufReader, BufWriter, Read, Write};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    /// Identifies a PathData snapshot file
+    const MAGIC: &[u8; 4] = b"HASP";
+
+    /// Bumped whenever the layout below changes, old snapshots are then rejected
+    /// and the MRT files must be re-parsed.
+    ///
+    /// Layout (all integers are LEB128 varints, strings are length prefixed UTF-8):
+    ///  - magic, format version, tool version, creation time, source files
+    ///  - tables of the sources (filename, collector and snapshot), peers, AS paths, communities and large communities
+    ///  - each origin with its AS paths, and each AS path with its routes, where a
+    ///    route refers to the tables by index
+    ///  - the AS set routes
+    pub const FORMAT_VERSION: u64 = 5;
+
+    /// The header at the start of every snapshot
+    #[derive(Debug, PartialEq)]
+    pub struct SnapshotHeader {
+        pub format_version: u64,
+        /// Version of this tool which wrote the snapshot
+        pub tool_version: String,
+        /// RFC 3339 UTC timestamp
+        pub created: String,
+        /// The MRT files which were parsed to build the PathData
+        pub source_files: Vec<String>,
+    }
+
+    impl SnapshotHeader {
+        pub fn new(source_files: Vec<String>) -> Self {
+            SnapshotHeader {
+                format_version: FORMAT_VERSION,
+                tool_version: env!("CARGO_PKG_VERSION").to_string(),
+                created: chrono::Utc::now().to_rfc3339(),
+                source_files,
+            }
+        }
+    }
+
+    /// Values which are shared by many routes are written once, routes store the index
+    #[derive(Default)]
+    struct Tables {
+        sources: IndexSet<RouteSource>,
+        peers: IndexSet<PeerId>,
+        as_paths: IndexSet<AsPathId>,
+        communities: IndexSet<SharedCommunities>,
+        large_communities: IndexSet<SharedLargeCommunities>,
+    }
+
+    impl Tables {
+        fn new(origins: &[&OriginAsPaths], as_set_routes: &IndexSet<Route>) -> Self {
+            let mut tables = Tables::default();
+            for as_path in origins.iter().flat_map(|origin| origin.get_as_paths()) {
+                tables.as_paths.insert(as_path.get_as_path_id());
+                for route in as_path.get_routes() {
+                    tables.add_route(route);
+                }
+            }
+            for route in as_set_routes {
+                tables.add_route(route);
+            }
+            tables
+        }
+
+        fn add_route(&mut self, route: &Route) {
+            self.sources.insert(route.get_source());
+            self.peers.insert(route.get_peer_id());
+            self.as_paths.insert(route.get_as_path_id());
+            self.communities.insert(*route.get_shared_communities());
+            self.large_communities
+                .insert(*route.get_shared_large_communities());
+        }
+    }
+
+    fn get_index<T: Eq + Hash>(table: &IndexSet<T>, value: &T) -> u64 {
+        table.get_index_of(value).unwrap() as u64
+    }
+
+    fn get_entry<'a, T>(table: &'a [T], index: u64, name: &str) -> Result<&'a T, String> {
+        table.get(index as usize).ok_or_else(|| {
+            format!(
+                "Corrupt snapshot, {} index {} out of range ({})",
+                name,
+                index,
+                table.len()
+            )
+        })
+    }
+
+    struct SnapshotWriter<W: Write> {
+        writer: W,
+    }
+
+    impl<W: Write> SnapshotWriter<W> {
+        fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.writer.write_all(bytes)
+        }
+
+        fn write_u8(&mut self, value: u8) -> io::Result<()> {
+            self.write_bytes(&[value])?;
+            Ok(())
+        }
+
+        fn write_varint(&mut self, mut value: u64) -> io::Result<()> {
+            while value >= 0x80 {
+                self.write_u8((value as u8) | 0x80)?;
+                value >>= 7;
+            }
+            self.write_u8(value as u8)?;
+            Ok(())
+        }
+
+        fn write_str(&mut self, value: &str) -> io::Result<()> {
+            self.write_varint(value.len() as u64)?;
+            self.write_bytes(value.as_bytes())?;
+            Ok(())
+        }
+
+        /// The 2/4 byte flag is kept, as it's needed to re-encode the raw AS paths
+        fn write_asn(&mut self, asn: &Asn) -> io::Result<()> {
+            self.write_varint(((asn.to_u32() as u64) << 1) | asn.is_four_byte() as u64)?;
+            Ok(())
+        }
+
+        fn write_asns(&mut self, asns: &[Asn]) -> io::Result<()> {
+            self.write_varint(asns.len() as u64)?;
+            for asn in asns {
+                self.write_asn(asn)?;
+            }
+            Ok(())
+        }
+
+        fn write_ip_addr(&mut self, ip_addr: &IpAddr) -> io::Result<()> {
+            match ip_addr {
+                IpAddr::V4(ip) => {
+                    self.write_u8(4)?;
+                    self.write_bytes(&ip.octets())?;
+                }
+                IpAddr::V6(ip) => {
+                    self.write_u8(6)?;
+                    self.write_bytes(&ip.octets())?;
+                }
+            }
+            Ok(())
+        }
+
+        fn write_ip_net(&mut self, ip_net: &IpNet) -> io::Result<()> {
+            self.write_ip_addr(&ip_net.addr())?;
+            self.write_u8(ip_net.prefix_len())?;
+            Ok(())
+        }
+
+        fn write_peer(&mut self, peer: &Peer) -> io::Result<()> {
+            self.write_u8(peer.peer_type.bits())?;
+            self.write_bytes(&peer.peer_bgp_id.octets())?;
+            self.write_ip_addr(&peer.peer_ip)?;
+            self.write_asn(&peer.peer_asn)?;
+            Ok(())
+        }
+
+        fn write_community(&mut self, community: &Community) -> io::Result<()> {
+            match community {
+                Community::NoExport => self.write_u8(0)?,
+                Community::NoAdvertise => self.write_u8(1)?,
+                Community::NoExportSubConfed => self.write_u8(2)?,
+                Community::Custom(asn, value) => {
+                    self.write_u8(3)?;
+                    self.write_asn(asn)?;
+                    self.write_varint(*value as u64)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn write_large_community(&mut self, large_community: &LargeCommunity) -> io::Result<()> {
+            self.write_varint(large_community.global_admin as u64)?;
+            self.write_varint(large_community.local_data[0] as u64)?;
+            self.write_varint(large_community.local_data[1] as u64)?;
+            Ok(())
+        }
+
+        fn write_bgp_as_path(&mut self, as_path: &BgpAsPath) -> io::Result<()> {
+            self.write_varint(as_path.segments.len() as u64)?;
+            for segment in &as_path.segments {
+                let (segment_type, asns) = match segment {
+                    AsPathSegment::AsSequence(asns) => (0, asns),
+                    AsPathSegment::AsSet(asns) => (1, asns),
+                    AsPathSegment::ConfedSequence(asns) => (2, asns),
+                    AsPathSegment::ConfedSet(asns) => (3, asns),
+                };
+                self.write_u8(segment_type)?;
+                self.write_asns(asns)?;
+            }
+            Ok(())
+        }
+
+        fn write_positioned_segment(&mut self, segment: &PositionedSegment) -> io::Result<()> {
+            self.write_varint(segment.position as u64)?;
+            self.write_asns(&segment.asns)?;
+            Ok(())
+        }
+
+        fn write_confed_segment(&mut self, confed_segment: &ConfedSegment) -> io::Result<()> {
+            self.write_u8(match confed_segment.segment_type {
+                ConfedSegmentType::Sequence => 0,
+                ConfedSegmentType::Set => 1,
+            })?;
+            self.write_positioned_segment(&confed_segment.segment)?;
+            Ok(())
+        }
+
+        fn write_header(&mut self, header: &SnapshotHeader) -> io::Result<()> {
+            self.write_bytes(MAGIC)?;
+            self.write_varint(header.format_version)?;
+            self.write_str(&header.tool_version)?;
+            self.write_str(&header.created)?;
+            self.write_varint(header.source_files.len() as u64)?;
+            for source_file in &header.source_files {
+                self.write_str(source_file)?;
+            }
+            Ok(())
+        }
+
+        fn write_tables(&mut self, tables: &Tables) -> io::Result<()> {
+            self.write_varint(tables.sources.len() as u64)?;
+            for source in &tables.sources {
+                self.write_str(source.filename.get())?;
+                self.write_str(source.collector.get())?;
+                self.write_str(source.snapshot.get())?;
+            }
+            self.write_varint(tables.peers.len() as u64)?;
+            for peer in &tables.peers {
+                self.write_peer(peer.get())?;
+            }
+            self.write_varint(tables.as_paths.len() as u64)?;
+            for as_path in &tables.as_paths {
+                self.write_asns(as_path.get())?;
+            }
+            self.write_varint(tables.communities.len() as u64)?;
+            for communities in &tables.communities {
+                self.write_varint(communities.get_communities().len() as u64)?;
+                for community in communities.get_communities() {
+                    self.write_community(community)?;
+                }
+            }
+            self.write_varint(tables.large_communities.len() as u64)?;
+            for large_communities in &tables.large_communities {
+                self.write_varint(large_communities.get_large_communities().len() as u64)?;
+                for large_community in large_communities.get_large_communities() {
+                    self.write_large_community(large_community)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn write_route(&mut self, route: &Route, tables: &Tables) -> io::Result<()> {
+            self.write_varint(get_index(&tables.as_paths, &route.get_as_path_id()))?;
+            self.write_varint(route.get_confed_segments().len() as u64)?;
+            for confed_segment in route.get_confed_segments() {
+                self.write_confed_segment(confed_segment)?;
+            }
+            self.write_varint(route.get_as_sets().len() as u64)?;
+            for as_set in route.get_as_sets() {
+                self.write_positioned_segment(as_set)?;
+            }
+            match route.get_raw_as_paths() {
+                Some(raw_as_paths) => {
+                    self.write_u8(1)?;
+                    self.write_bgp_as_path(&raw_as_paths.as_path)?;
+                    self.write_bgp_as_path(&raw_as_paths.as4_path)?;
+                }
+                None => self.write_u8(0)?,
+            }
+            self.write_varint(get_index(&tables.sources, &route.get_source()))?;
+            self.write_ip_addr(route.get_next_hop())?;
+            self.write_varint(get_index(&tables.peers, &route.get_peer_id()))?;
+            self.write_ip_net(route.get_prefix())?;
+            self.write_varint(get_index(
+                &tables.communities,
+                route.get_shared_communities(),
+            ))?;
+            self.write_varint(get_index(
+                &tables.large_communities,
+                route.get_shared_large_communities(),
+            ))?;
+            match route.get_aggregator() {
+                Some(aggregator) => {
+                    self.write_u8(1)?;
+                    self.write_asn(&aggregator.asn)?;
+                    self.write_bytes(&aggregator.ip.octets())?;
+                }
+                None => self.write_u8(0)?,
+            }
+            Ok(())
+        }
+
+        fn write_origins(&mut self, origins: &[&OriginAsPaths], tables: &Tables) -> io::Result<()> {
+            self.write_varint(origins.len() as u64)?;
+            for origin_as_paths in origins {
+                self.write_asn(origin_as_paths.get_origin())?;
+                self.write_varint(origin_as_paths.len() as u64)?;
+                for as_path in origin_as_paths.get_as_paths() {
+                    self.write_varint(get_index(&tables.as_paths, &as_path.get_as_path_id()))?;
+                    self.write_varint(as_path.get_routes().len() as u64)?;
+                    for route in as_path.get_routes() {
+                        self.write_route(route, tables)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn write_as_set_routes(
+            &mut self,
+            as_set_routes: &IndexSet<Route>,
+            tables: &Tables,
+        ) -> io::Result<()> {
+            self.write_varint(as_set_routes.len() as u64)?;
+            for route in as_set_routes {
+                self.write_route(route, tables)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Interned values read from the tables, in table order
+    struct LoadedTables {
+        sources: Vec<RouteSource>,
+        peers: Vec<PeerId>,
+        /// The raw and deduped AS path
+        as_paths: Vec<(AsPathId, AsPathId)>,
+        communities: Vec<SharedCommunities>,
+        large_communities: Vec<SharedLargeCommunities>,
+    }
+
+    /// Reads a snapshot, every read fails rather than panics on a corrupt or truncated
+    /// snapshot. The remaining size bounds every length read from the snapshot, so a
+    /// corrupt length can't cause a huge allocation.
+    struct SnapshotReader<R: Read> {
+        reader: R,
+        remaining: u64,
+    }
+
+    impl<R: Read> SnapshotReader<R> {
+        fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+            let mut bytes = [0u8; N];
+            self.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        fn read_exact(&mut self, bytes: &mut [u8]) -> Result<(), String> {
+            if bytes.len() as u64 > self.remaining {
+                return Err(String::from("Corrupt snapshot, truncated"));
+            }
+            self.reader
+                .read_exact(bytes)
+                .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+            self.remaining -= bytes.len() as u64;
+            Ok(())
+        }
+
+        fn read_u8(&mut self) -> Result<u8, String> {
+            Ok(self.read_bytes::<1>()?[0])
+        }
+
+        fn read_varint(&mut self) -> Result<u64, String> {
+            let mut value = 0u64;
+            for shift in (0..64).step_by(7) {
+                let byte = self.read_u8()?;
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(value);
+                }
+            }
+            Err(String::from(
+                "Corrupt snapshot, varint is longer than 64 bits",
+            ))
+        }
+
+        /// Every item takes at least one byte, so a length can't exceed the remaining size
+        fn read_len(&mut self) -> Result<usize, String> {
+            let len = self.read_varint()?;
+            if len > self.remaining {
+                return Err(format!(
+                    "Corrupt snapshot, length {} exceeds the remaining {} bytes",
+                    len, self.remaining
+                ));
+            }
+            Ok(len as usize)
+        }
+
+        fn read_str(&mut self) -> Result<String, String> {
+            let mut bytes = vec![0u8; self.read_len()?];
+            self.read_exact(&mut bytes)?;
+            String::from_utf8(bytes).map_err(|e| format!("Corrupt snapshot, invalid string: {}", e))
+        }
+
+        fn read_asn(&mut self) -> Result<Asn, String> {
+            let value = self.read_varint()?;
+            let asn = (value >> 1) as u32;
+            if value & 1 == 1 {
+                Ok(Asn::new_32bit(asn))
+            } else {
+                Ok(Asn::new_16bit(asn as u16))
+            }
+        }
+
+        fn read_asns(&mut self) -> Result<Vec<Asn>, String> {
+            let len = self.read_len()?;
+            (0..len).map(|_| self.read_asn()).collect()
+        }
+
+        fn read_ip_addr(&mut self) -> Result<IpAddr, String> {
+            match self.read_u8()? {
+                4 => Ok(IpAddr::V4(Ipv4Addr::from(self.read_bytes::<4>()?))),
+                6 => Ok(IpAddr::V6(Ipv6Addr::from(self.read_bytes::<16>()?))),
+                family => Err(format!("Corrupt snapshot, unknown IP family {}", family)),
+            }
+        }
+
+        fn read_ip_net(&mut self) -> Result<IpNet, String> {
+            let ip_addr = self.read_ip_addr()?;
+            let prefix_len = self.read_u8()?;
+            IpNet::new(ip_addr, prefix_len)
+                .map_err(|e| format!("Corrupt snapshot, invalid prefix: {}", e))
+        }
+
+        fn read_peer(&mut self) -> Result<Peer, String> {
+            Ok(Peer {
+                peer_type: PeerType::from_bits_retain(self.read_u8()?),
+                peer_bgp_id: Ipv4Addr::from(self.read_bytes::<4>()?),
+                peer_ip: self.read_ip_addr()?,
+                peer_asn: self.read_asn()?,
+            })
+        }
+
+        fn read_community(&mut self) -> Result<Community, String> {
+            match self.read_u8()? {
+                0 => Ok(Community::NoExport),
+                1 => Ok(Community::NoAdvertise),
+                2 => Ok(Community::NoExportSubConfed),
+                3 => Ok(Community::Custom(
+                    self.read_asn()?,
+                    self.read_varint()? as u16,
+                )),
+                tag => Err(format!("Corrupt snapshot, unknown community type {}", tag)),
+            }
+        }
+
+        fn read_large_community(&mut self) -> Result<LargeCommunity, String> {
+            Ok(LargeCommunity {
+                global_admin: self.read_varint()? as u32,
+                local_data: [self.read_varint()? as u32, self.read_varint()? as u32],
+            })
+        }
+
+        fn read_bgp_as_path(&mut self) -> Result<BgpAsPath, String> {
+            let len = self.read_len()?;
+            let segments = (0..len)
+                .map(|_| {
+                    let segment_type = self.read_u8()?;
+                    let asns = self.read_asns()?;
+                    match segment_type {
+                        0 => Ok(AsPathSegment::AsSequence(asns)),
+                        1 => Ok(AsPathSegment::AsSet(asns)),
+                        2 => Ok(AsPathSegment::ConfedSequence(asns)),
+                        3 => Ok(AsPathSegment::ConfedSet(asns)),
+                        _ => Err(format!(
+                            "Corrupt snapshot, unknown segment type {}",
+                            segment_type
+                        )),
+                    }
+                })
+                .collect::<Result<_, String>>()?;
+            Ok(BgpAsPath { segments })
+        }
+
+        fn read_positioned_segment(&mut self) -> Result<PositionedSegment, String> {
+            Ok(PositionedSegment {
+                position: self.read_varint()? as usize,
+                asns: self.read_asns()?,
+            })
+        }
+
+        fn read_confed_segment(&mut self) -> Result<ConfedSegment, String> {
+            let segment_type = match self.read_u8()? {
+                0 => ConfedSegmentType::Sequence,
+                1 => ConfedSegmentType::Set,
+                tag => {
+                    return Err(format!(
+                        "Corrupt snapshot, unknown confed segment type {}",
+                        tag
+                    ));
+                }
+            };
+            Ok(ConfedSegment {
+                segment_type,
+                segment: self.read_positioned_segment()?,
+            })
+        }
+
+        fn read_header(&mut self) -> Result<SnapshotHeader, String> {
+            if self.read_bytes::<4>()? != *MAGIC {
+                return Err(String::from("Not a snapshot file"));
+            }
+            let format_version = self.read_varint()?;
+            if format_version != FORMAT_VERSION {
+                return Err(format!(
+                    "Unsupported snapshot format version {}, expected {}",
+                    format_version, FORMAT_VERSION
+                ));
+            }
+            let tool_version = self.read_str()?;
+            let created = self.read_str()?;
+            let len = self.read_len()?;
+            let source_files = (0..len)
+                .map(|_| self.read_str())
+                .collect::<Result<_, String>>()?;
+            Ok(SnapshotHeader {
+                format_version,
+                tool_version,
+                created,
+                source_files,
+            })
+        }
+
+        /// Every table value is interned once, rather than once per route
+        fn read_tables(&mut self) -> Result<LoadedTables, String> {
+            let len = self.read_len()?;
+            let sources = (0..len)
+                .map(|_| {
+                    let filename = self.read_str()?;
+                    let collector = self.read_str()?;
+                    let snapshot = self.read_str()?;
+                    Ok(RouteSource::new(&filename, &collector, &snapshot))
+                })
+                .collect::<Result<_, String>>()?;
+            let len = self.read_len()?;
+            let peers = (0..len)
+                .map(|_| Ok(PeerId::new(self.read_peer()?)))
+                .collect::<Result<_, String>>()?;
+            let len = self.read_len()?;
+            let as_paths = (0..len)
+                .map(|_| {
+                    let as_path = self.read_asns()?;
+                    let mut deduped_as_path = as_path.clone();
+                    deduped_as_path.dedup();
+                    Ok((AsPathId::new(as_path), AsPathId::new(deduped_as_path)))
+                })
+                .collect::<Result<_, String>>()?;
+            let len = self.read_len()?;
+            let communities = (0..len)
+                .map(|_| {
+                    let len = self.read_len()?;
+                    Ok(SharedCommunities::new(
+                        (0..len)
+                            .map(|_| self.read_community())
+                            .collect::<Result<_, String>>()?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?;
+            let len = self.read_len()?;
+            let large_communities = (0..len)
+                .map(|_| {
+                    let len = self.read_len()?;
+                    Ok(SharedLargeCommunities::new(
+                        (0..len)
+                            .map(|_| self.read_large_community())
+                            .collect::<Result<_, String>>()?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?;
+            Ok(LoadedTables {
+                sources,
+                peers,
+                as_paths,
+                communities,
+                large_communities,
+            })
+        }
+
+        fn read_route(&mut self, tables: &LoadedTables) -> Result<Route, String> {
+            let (as_path, deduped_as_path) =
+                *get_entry(&tables.as_paths, self.read_varint()?, "AS path")?;
+            let len = self.read_len()?;
+            let confed_segments = (0..len)
+                .map(|_| self.read_confed_segment())
+                .collect::<Result<_, String>>()?;
+            let len = self.read_len()?;
+            let as_sets = (0..len)
+                .map(|_| self.read_positioned_segment())
+                .collect::<Result<_, String>>()?;
+            let raw_as_paths = match self.read_u8()? {
+                0 => None,
+                _ => Some(RawAsPathsId::new(RawAsPaths {
+                    as_path: self.read_bgp_as_path()?,
+                    as4_path: self.read_bgp_as_path()?,
+                })),
+            };
+            let source = *get_entry(&tables.sources, self.read_varint()?, "source")?;
+            let next_hop = self.read_ip_addr()?;
+            let peer = *get_entry(&tables.peers, self.read_varint()?, "peer")?;
+            let prefix = self.read_ip_net()?;
+            let communities = get_entry(&tables.communities, self.read_varint()?, "communities")?;
+            let large_communities = get_entry(
+                &tables.large_communities,
+                self.read_varint()?,
+                "large communities",
+            )?;
+            let aggregator = match self.read_u8()? {
+                0 => None,
+                _ => Some(Aggregator {
+                    asn: self.read_asn()?,
+                    ip: Ipv4Addr::from(self.read_bytes::<4>()?),
+                }),
+            };
+            Ok(Route::from_interned(
+                as_path,
+                deduped_as_path,
+                confed_segments,
+                as_sets,
+                raw_as_paths,
+                source,
+                next_hop,
+                peer,
+                prefix,
+                *communities,
+                *large_communities,
+                aggregator,
+            ))
+        }
+
+        fn read_origins(&mut self, tables: &LoadedTables) -> Result<PathData, String> {
+            let mut path_data = PathData::new();
+            let origins = self.read_len()?;
+            for _ in 0..origins {
+                let mut origin_as_paths = OriginAsPaths::new(self.read_asn()?);
+                let as_paths = self.read_len()?;
+                for _ in 0..as_paths {
+                    let (as_path_id, _) =
+                        *get_entry(&tables.as_paths, self.read_varint()?, "AS path")?;
+                    let mut as_path = AsPath::new(as_path_id.get().clone());
+                    let routes = self.read_len()?;
+                    for _ in 0..routes {
+                        as_path.add_route(self.read_route(tables)?);
+                    }
+                    origin_as_paths.add_as_path(as_path);
+                }
+                path_data.insert_origin_as_paths(origin_as_paths);
+            }
+            Ok(path_data)
+        }
+
+        fn read_as_set_routes(
+            &mut self,
+            tables: &LoadedTables,
+            path_data: &mut PathData,
+        ) -> Result<(), String> {
+            let routes = self.read_len()?;
+            for _ in 0..routes {
+                path_data.insert_route(self.read_route(tables)?);
+            }
+            Ok(())
+        }
+    }
+
+    /// Write the header and PathData, origins are sorted so the same PathData
+    /// always produces the same snapshot (apart from the header)
+    pub fn write_snapshot<W: Write>(
+        header: &SnapshotHeader,
+        path_data: &PathData,
+        writer: W,
+    ) -> io::Result<()> {
+        let mut origins: Vec<&OriginAsPaths> = path_data.get_as_paths().collect();
+        origins.sort_by_key(|origin_as_paths| *origin_as_paths.get_origin());
+        let tables = Tables::new(&origins, path_data.get_as_set_routes());
+
+        let mut snapshot_writer = SnapshotWriter { writer };
+        snapshot_writer.write_header(header)?;
+        snapshot_writer.write_tables(&tables)?;
+        snapshot_writer.write_origins(&origins, &tables)?;
+        snapshot_writer.write_as_set_routes(path_data.get_as_set_routes(), &tables)?;
+        snapshot_writer.writer.flush()
+    }
+
+    /// Read the header and PathData, size is the length of the snapshot in bytes.
+    /// Returns an error if the snapshot is corrupt, truncated or from another format version.
+    pub fn read_snapshot<R: Read>(
+        reader: R,
+        size: u64,
+    ) -> Result<(SnapshotHeader, PathData), String> {
+        let mut snapshot_reader = SnapshotReader {
+            reader,
+            remaining: size,
+        };
+        let header = snapshot_reader.read_header()?;
+        let tables = snapshot_reader.read_tables()?;
+        let mut path_data = snapshot_reader.read_origins(&tables)?;
+        snapshot_reader.read_as_set_routes(&tables, &mut path_data)?;
+        if snapshot_reader.remaining != 0 {
+            return Err(format!(
+                "Corrupt snapshot, {} bytes left after the last route",
+                snapshot_reader.remaining
+            ));
+        }
+        Ok((header, path_data))
+    }
+
+    /// Save the merged and filtered PathData, so it can be searched again without
+    /// re-parsing the MRT files it was built from
+    pub fn save_path_data(
+        path_data: &PathData,
+        source_files: Vec<String>,
+        filename: &str,
+    ) -> io::Result<()> {
+        info!("Saving snapshot to {}", filename);
+        let with_filename = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", filename, e));
+        let file = File::create(filename).map_err(with_filename)?;
+        write_snapshot(
+            &SnapshotHeader::new(source_files),
+            path_data,
+            BufWriter::new(file),
+        )
+        .map_err(with_filename)?;
+        info!(
+            "Saved {} origins, {} AS paths, {} routes",
+            path_data.get_origins_count(),
+            path_data.get_as_paths_count(),
+            path_data.get_routes_count()
+        );
+        Ok(())
+    }
+
+    pub fn load_path_data(filename: &str) -> Result<PathData, String> {
+        info!("Loading snapshot from {}", filename);
+        let file = File::open(filename)
+            .map_err(|e| format!("Unable to open snapshot {}: {}", filename, e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("Unable to read snapshot size {}: {}", filename, e))?
+            .len();
+        let (header, path_data) = read_snapshot(BufReader::new(file), size)
+            .map_err(|e| format!("{} in {}", e, filename))?;
+
+        info!(
+            "Snapshot created {} by version {} from {} files: {:?}",
+            header.created,
+            header.tool_version,
+            header.source_files.len(),
+            header.source_files
+        );
+        if header.tool_version != env!("CARGO_PKG_VERSION") {
+            warn!(
+                "Snapshot was created by version {}, this is version {}",
+                header.tool_version,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+        info!(
+            "Loaded {} origins, {} AS paths, {} routes",
+            path_data.get_origins_count(),
+            path_data.get_as_paths_count(),
+            path_data.get_routes_count()
+        );
+        Ok(path_data)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn get_path_data() -> PathData {
+            let mut path_data = PathData::new();
+            for (as_path, as_set, filename, prefix) in [
+                (Vec::from([1, 2, 2, 3]), None, "rib.a", "2001:db8::/32"),
+                (Vec::from([4, 2, 5, 3]), None, "rib.b", "2001:db8:1::/48"),
+                (Vec::from([1, 23456, 6]), None, "rib.a", "198.51.100.0/24"),
+                (Vec::from([1, 2]), Some(8), "rib.b", "198.51.0.0/16"),
+            ] {
+                let route = Route::mock(None)
+                    .with_as_path(&as_path)
+                    .with_confed_segments(Vec::from([ConfedSegment {
+                        segment_type: ConfedSegmentType::Set,
+                        segment: PositionedSegment {
+                            position: 1,
+                            asns: Vec::from([Asn::new_16bit(65000)]),
+                        },
+                    }]))
+                    .with_raw_as_paths(RawAsPaths {
+                        as_path: BgpAsPath::from_segments(Vec::from([AsPathSegment::AsSequence(
+                            as_path.iter().map(|a| Asn::new_32bit(*a)).collect(),
+                        )])),
+                        as4_path: BgpAsPath::from_segments(Vec::from([AsPathSegment::AsSet(
+                            Vec::from([Asn::new_32bit(7)]),
+                        )])),
+                    })
+                    .with_filename(filename)
+                    .with_prefix(prefix);
+                path_data.insert_route(match as_set {
+                    Some(asn) => route
+                        .with_as_set(&[asn, asn + 1])
+                        .with_aggregator(asn)
+                        .build(),
+                    None => route.build(),
+                });
+            }
+            path_data
+        }
+
+        #[test]
+        fn test_snapshot_round_trip() {
+            let path_data = get_path_data();
+            let header = SnapshotHeader::new(Vec::from(["rib.a".to_string(), "-".to_string()]));
+            let mut snapshot = Vec::<u8>::new();
+            write_snapshot(&header, &path_data, &mut snapshot).unwrap();
+
+            let (loaded_header, loaded) =
+                read_snapshot(snapshot.as_slice(), snapshot.len() as u64).unwrap();
+            assert_eq!(loaded_header, header);
+            assert_eq!(loaded, path_data);
+            assert_eq!(loaded.get_routes_count(), 4);
+            assert_eq!(loaded.get_as_set_routes().len(), 1);
+
+            // Fields which aren't part of a route's identity are kept too
+            let route = loaded
+                .get_as_paths()
+                .flat_map(|origin| origin.get_as_paths())
+                .flat_map(|as_path| as_path.get_routes())
+                .find(|route| route.get_filename() == "rib.b")
+                .unwrap();
+            assert_eq!(route.get_prepend_counts(), Vec::from([1, 1, 1, 1]));
+            assert!(route.get_raw_as_paths().is_some());
+        }
+
+        fn get_snapshot() -> Vec<u8> {
+            let mut snapshot = Vec::<u8>::new();
+            write_snapshot(
+                &SnapshotHeader::new(Vec::new()),
+                &get_path_data(),
+                &mut snapshot,
+            )
+            .unwrap();
+            snapshot
+        }
+
+        #[test]
+        fn test_snapshot_write_error() {
+            // A writer which fills up part way through the snapshot
+            let size = get_snapshot().len();
+            for len in [0, MAGIC.len(), size / 2, size - 1] {
+                let mut buffer = vec![0; len];
+                let error = write_snapshot(
+                    &SnapshotHeader::new(Vec::new()),
+                    &get_path_data(),
+                    buffer.as_mut_slice(),
+                )
+                .unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+            }
+
+            let filename = std::env::temp_dir()
+                .join(format!("hidden-as-paths-missing-{}", std::process::id()))
+                .join("snapshot");
+            let filename = filename.to_str().unwrap();
+            let error = save_path_data(&get_path_data(), Vec::new(), filename).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::NotFound);
+            assert!(error.to_string().starts_with(filename));
+        }
+
+        fn get_read_error(snapshot: &[u8]) -> String {
+            read_snapshot(snapshot, snapshot.len() as u64).unwrap_err()
+        }
+
+        #[test]
+        fn test_snapshot_format_version() {
+            let mut snapshot = get_snapshot();
+            snapshot[MAGIC.len()] = (FORMAT_VERSION + 1) as u8;
+            assert!(get_read_error(&snapshot).starts_with("Unsupported snapshot format version"));
+
+            snapshot[0] = b'X';
+            assert_eq!(get_read_error(&snapshot), "Not a snapshot file");
+        }
+
+        #[test]
+        fn test_snapshot_corrupt() {
+            let snapshot = get_snapshot();
+            for len in [2, MAGIC.len() + 1, snapshot.len() / 2, snapshot.len() - 1] {
+                assert!(get_read_error(&snapshot[..len]).starts_with("Corrupt snapshot"));
+            }
+
+            // A huge length is rejected before anything is allocated
+            let mut corrupt = snapshot[..MAGIC.len() + 1].to_vec();
+            corrupt.extend([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+            assert!(get_read_error(&corrupt).contains("exceeds the remaining"));
+
+            // A snapshot can't be followed by anything else
+            let mut extended = snapshot.clone();
+            extended.push(0);
+            assert!(get_read_error(&extended).contains("left after the last route"));
+        }
+
+        /// Run with: cargo test --release bench_load_snapshot -- --ignored --nocapture
+        /// Loading reads each table once and each route once, so 4x the routes should
+        /// take roughly 4x the time.
+        #[test]
+        #[ignore]
+        fn bench_load_snapshot() {
+            let time_load = |prefixes: u32| {
+                let mut path_data = PathData::new();
+                for peer_id in 0..20 {
+                    for p in 0..prefixes {
+                        let origin = 100_000 + p / 5;
+                        path_data.insert_route(
+                            Route::mock(None)
+                                .with_as_path(&[1000 + peer_id, 3356, 200 + p % 50, origin])
+                                .with_peer(1000 + peer_id)
+                                .with_prefix(&format!("{}/24", Ipv4Addr::from(p << 8)))
+                                .build(),
+                        );
+                    }
+                }
+                let mut snapshot = Vec::<u8>::new();
+                write_snapshot(&SnapshotHeader::new(Vec::new()), &path_data, &mut snapshot)
+                    .unwrap();
+
+                let start = std::time::Instant::now();
+                let (_, loaded) =
+                    read_snapshot(snapshot.as_slice(), snapshot.len() as u64).unwrap();
+                assert_eq!(loaded.get_routes_count(), path_data.get_routes_count());
+                (start.elapsed(), snapshot.len())
+            };
+            let (small, small_size) = time_load(25_000);
+            let (large, large_size) = time_load(100_000);
+            println!(
+                "Loaded 500k routes ({} bytes) in {:?}, 2M routes ({} bytes) in {:?}",
+                small_size, small, large_size, large
+            );
+            assert!(large < small * 8);
+        }
+    }
+}